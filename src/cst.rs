@@ -0,0 +1,631 @@
+use lex::{Lex,Token,TokenType};
+use options::ParserOptions;
+use parse_error::Result;
+use parser::JsonParser;
+use pointer::JsonPointer;
+use std::error::Error;
+use std::fmt;
+
+/// A lossless syntax tree of a JSON document
+///
+/// The tree keeps the exact text it was parsed from: whitespace,
+/// comments, number spellings and string escapes all survive. Values
+/// are located by path and edited in place with JSON text fragments;
+/// an edit changes only the bytes of the affected value or member, and
+/// `to_string` gives back the edited text. Raw text edits, such as an
+/// editor's keystrokes, go through `apply_edit`.
+#[derive(Debug,Clone)]
+pub struct SyntaxTree
+{
+    text : String,
+    options : ParserOptions,
+    root : SyntaxNode,
+}
+
+/// The kind of value a node stands for
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum NodeKind
+{
+    Object,
+    Array,
+    String,
+    Number,
+    Bool,
+    Null,
+}
+
+/// A value in a syntax tree and where it is written
+#[derive(Debug,Clone,PartialEq)]
+pub struct SyntaxNode
+{
+    kind : NodeKind,
+    start : usize,
+    end : usize,
+    children : Vec<Child>,
+}
+
+/// A member or element of an object or array node
+#[derive(Debug,Clone,PartialEq)]
+struct Child
+{
+    /// The decoded key of a member
+    key : Option<String>,
+    /// Offset of the key of a member, or of the value of an element
+    start : usize,
+    value : SyntaxNode,
+    /// Offset just after the comma following the child, if there is one
+    comma : Option<usize>,
+}
+
+/// Why an edit of a `SyntaxTree` failed
+#[derive(Debug,Clone,PartialEq)]
+pub enum EditError
+{
+    /// Nothing is at the path, or its parent does not exist
+    NotFound(JsonPointer),
+    /// The object already has a member with that key
+    AlreadyExists(JsonPointer),
+    /// The parent of the path is not an object or array,
+    /// or the path is the root, which has no parent
+    NoContainer(JsonPointer),
+    /// The fragment is not a single value of the tree's dialect
+    InvalidFragment(String),
+    /// The range of a text edit is reversed, past the end of the text
+    /// or not on character boundaries
+    InvalidRange(usize, usize),
+    /// The text after an edit is not a well-formed document
+    InvalidText(String),
+}
+
+impl fmt::Display for EditError
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self
+        {
+            EditError::NotFound(ref path) => write!(f, "No value at {:?}", path.to_string()),
+            EditError::AlreadyExists(ref path) => write!(f, "A value already exists at {:?}", path.to_string()),
+            EditError::NoContainer(ref path) => write!(f, "No object or array to hold {:?}", path.to_string()),
+            EditError::InvalidFragment(ref message) => write!(f, "Invalid JSON fragment: {}", message),
+            EditError::InvalidRange(start, end) => write!(f, "Invalid text range {}..{}", start, end),
+            EditError::InvalidText(ref message) => write!(f, "Edit leaves an invalid document: {}", message),
+        }
+    }
+}
+
+impl Error for EditError
+{
+    fn description(&self) -> &str {
+        "Invalid edit of a syntax tree"
+    }
+}
+
+impl SyntaxNode
+{
+    pub fn kind(&self) -> NodeKind
+    {
+        self.kind
+    }
+
+    /// Byte offsets of the start and end of the value in the text
+    pub fn span(&self) -> (usize, usize)
+    {
+        (self.start, self.end)
+    }
+
+    /// Number of members or elements
+    pub fn len(&self) -> usize
+    {
+        self.children.len()
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.children.is_empty()
+    }
+
+    /// The member with key `segment`, or the element at index `segment`
+    ///
+    /// If an object repeats the key, the last member is found,
+    /// the one that wins when the document is parsed
+    pub fn child(&self, segment : &str) -> Option<&SyntaxNode>
+    {
+        self.position(segment).map(|i| &self.children[i].value)
+    }
+
+    fn position(&self, segment : &str) -> Option<usize>
+    {
+        match self.kind
+        {
+            NodeKind::Object => self.children.iter().rposition(|c| c.key.as_ref().map_or(false, |k| k == segment)),
+            NodeKind::Array => match array_index(segment)
+            {
+                Some(index) if index < self.children.len() => Some(index),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// True if an edit from `start` to `end` falls within the value,
+    /// and for an object or array within its brackets
+    fn encloses(&self, start : usize, end : usize) -> bool
+    {
+        match self.kind
+        {
+            NodeKind::Object | NodeKind::Array => self.start < start && end < self.end,
+            _ => self.start <= start && end <= self.end,
+        }
+    }
+}
+
+impl SyntaxTree
+{
+    /// Parse strict JSON into a syntax tree
+    pub fn parse<'src>(text : &'src str) -> Result<'src, SyntaxTree>
+    {
+        SyntaxTree::parse_with_options(text, ParserOptions::new())
+    }
+
+    /// Parse a document of the dialect enabled by `options`, such as JSONC
+    pub fn parse_with_options<'src>(text : &'src str, options : ParserOptions) -> Result<'src, SyntaxTree>
+    {
+        JsonParser::new(text).with_options(options).validate()?;
+        let root = build(text, options);
+        Ok(SyntaxTree{text : text.to_owned(), options, root})
+    }
+
+    /// The text of the document, including all edits
+    pub fn text(&self) -> &str
+    {
+        &self.text
+    }
+
+    pub fn root(&self) -> &SyntaxNode
+    {
+        &self.root
+    }
+
+    /// The node of the value at `path`
+    pub fn node(&self, path : &JsonPointer) -> Option<&SyntaxNode>
+    {
+        let mut node = &self.root;
+        for segment in path.segments()
+        {
+            node = node.child(segment)?;
+        }
+        Some(node)
+    }
+
+    /// The value at `path` exactly as it is written
+    pub fn get(&self, path : &JsonPointer) -> Option<&str>
+    {
+        self.node(path).map(|node| &self.text[node.start..node.end])
+    }
+
+    /// Replace the value at `path` with the JSON text `value`
+    pub fn set(&mut self, path : &JsonPointer, value : &str) -> ::std::result::Result<(), EditError>
+    {
+        let value = self.fragment(value)?;
+        let (start, end) = match self.node(path)
+        {
+            Some(node) => node.span(),
+            None => return Err(EditError::NotFound(path.clone())),
+        };
+        self.apply_edit(start, end, value).map(|_| ())
+    }
+
+    /// Insert the JSON text `value` at `path`
+    ///
+    /// The parent of `path` must be an object without that key, or an
+    /// array whose length is at least the index; the index `-` appends.
+    /// The new member or element copies the layout of its neighbours.
+    pub fn insert(&mut self, path : &JsonPointer, value : &str) -> ::std::result::Result<(), EditError>
+    {
+        let value = self.fragment(value)?;
+        let (parent, segment) = self.parent(path)?;
+
+        let (member, index) = match parent.kind
+        {
+            NodeKind::Object =>
+            {
+                if parent.position(segment).is_some()
+                {
+                    return Err(EditError::AlreadyExists(path.clone()));
+                }
+                // copy the text between key and value, such as ": "
+                let separator = parent.children.last().map_or(": ".to_owned(), |child| self.key_separator(child));
+                (format!("{}{}{}", quote(segment), separator, value), parent.children.len())
+            },
+            NodeKind::Array =>
+            {
+                let index = match array_index(segment)
+                {
+                    _ if segment == "-" => parent.children.len(),
+                    Some(index) if index <= parent.children.len() => index,
+                    _ => return Err(EditError::NotFound(path.clone())),
+                };
+                (value.to_owned(), index)
+            },
+            _ => return Err(EditError::NoContainer(path.clone())),
+        };
+
+        let (position, text) = if parent.children.is_empty()
+        {
+            (parent.start + 1, member)
+        }
+        else if index < parent.children.len()
+        {
+            // before an existing child, which keeps the layout before it;
+            // the first child has none to copy, so take the second's
+            let child = &parent.children[index];
+            let indent = match index
+            {
+                0 if parent.children.len() > 1 => self.indentation(parent, 1),
+                _ => self.indentation(parent, index),
+            };
+            (child.start, format!("{},{}", member, indent))
+        }
+        else
+        {
+            let last = &parent.children[index - 1];
+            let indent = self.indentation(parent, index - 1);
+            match last.comma
+            {
+                Some(comma) => (comma, format!("{}{},", indent, member)),
+                None => (last.value.end, format!(",{}{}", indent, member)),
+            }
+        };
+        self.apply_edit(position, position, &text).map(|_| ())
+    }
+
+    /// Remove the member or element at `path`, with its comma
+    pub fn remove(&mut self, path : &JsonPointer) -> ::std::result::Result<(), EditError>
+    {
+        let (parent, segment) = self.parent(path)?;
+        let index = match parent.position(segment)
+        {
+            Some(index) => index,
+            None => return Err(EditError::NotFound(path.clone())),
+        };
+
+        let child = &parent.children[index];
+        let child_end = child.comma.unwrap_or(child.value.end);
+        let (start, end) = if index + 1 < parent.children.len()
+        {
+            (child.start, parent.children[index + 1].start)
+        }
+        else if index > 0
+        {
+            let previous = &parent.children[index - 1];
+            match (child.comma, previous.comma)
+            {
+                (Some(_), Some(previous_comma)) => (previous_comma, child_end),
+                _ => (previous.value.end, child.value.end),
+            }
+        }
+        else
+        {
+            // the only child, drop the whitespace before it as well
+            let open = parent.start + 1;
+            let start = if self.text[open..child.start].trim().is_empty() {open} else {child.start};
+            (start, child_end)
+        };
+        self.apply_edit(start, end, "").map(|_| ())
+    }
+
+    /// Check that `value` is a single value, returning it without surrounding whitespace
+    fn fragment<'v>(&self, value : &'v str) -> ::std::result::Result<&'v str, EditError>
+    {
+        match JsonParser::new(value).with_options(self.options).validate()
+        {
+            Ok(_) => Ok(value.trim()),
+            Err(error) => Err(EditError::InvalidFragment(format!("{:?}", error))),
+        }
+    }
+
+    /// The container holding the value at `path`, and the last segment of `path`
+    fn parent<'p>(&self, path : &'p JsonPointer) -> ::std::result::Result<(&SyntaxNode, &'p str), EditError>
+    {
+        let segments = path.segments();
+        let (segment, ancestors) = match segments.split_last()
+        {
+            Some(split) => split,
+            None => return Err(EditError::NoContainer(path.clone())),
+        };
+        let mut parent = &self.root;
+        for ancestor in ancestors
+        {
+            parent = match parent.child(ancestor)
+            {
+                Some(node) => node,
+                None => return Err(EditError::NotFound(path.clone())),
+            };
+        }
+        match parent.kind
+        {
+            NodeKind::Object | NodeKind::Array => Ok((parent, segment)),
+            _ => Err(EditError::NoContainer(path.clone())),
+        }
+    }
+
+    /// The text between the comma or bracket before the child at
+    /// `index` and the child, if it is only whitespace
+    fn indentation(&self, parent : &SyntaxNode, index : usize) -> String
+    {
+        let before = match index
+        {
+            0 => parent.start + 1,
+            _ => parent.children[index - 1].comma.unwrap_or(parent.children[index - 1].value.end),
+        };
+        let between = &self.text[before..parent.children[index].start];
+        if between.trim().is_empty()
+        {
+            between.to_owned()
+        }
+        else
+        {
+            // keep only the layout after the last comment
+            let last_line = between.rfind('\n').map_or(" ", |i| &between[i..]);
+            if last_line.trim().is_empty() {last_line.to_owned()} else {" ".to_owned()}
+        }
+    }
+
+    /// The text between the key and the value of a member
+    fn key_separator(&self, child : &Child) -> String
+    {
+        let mut lexer = Lex::new(&self.text[child.start..child.value.start]).with_options(self.options);
+        let key = lexer.next();
+        let key_end = child.start + key.span.len();
+        self.text[key_end..child.value.start].to_owned()
+    }
+
+    /// Replace the bytes from `start` to `end` with `replacement`
+    ///
+    /// Only the innermost value enclosing the edit is lexed and parsed
+    /// again, falling back to the values around it when the edit does not
+    /// leave it a value on its own, and to the whole document last. The
+    /// spans after the edit move by the change in length. Returns the span
+    /// of the value that was parsed again; the tree is left unchanged if
+    /// the edited text is not a well-formed document.
+    pub fn apply_edit(&mut self, start : usize, end : usize, replacement : &str) -> ::std::result::Result<(usize, usize), EditError>
+    {
+        if start > end || end > self.text.len() || !self.text.is_char_boundary(start) || !self.text.is_char_boundary(end)
+        {
+            return Err(EditError::InvalidRange(start, end));
+        }
+        let text = format!("{}{}{}", &self.text[..start], replacement, &self.text[end..]);
+        let delta = replacement.len() as isize - (end - start) as isize;
+
+        // positions of the children enclosing the edit, innermost last
+        let mut indices = Vec::new();
+        if self.root.encloses(start, end)
+        {
+            let mut node = &self.root;
+            while let Some(i) = node.children.iter().position(|child| child.value.encloses(start, end))
+            {
+                indices.push(i);
+                node = &node.children[i].value;
+            }
+            loop
+            {
+                let (old_start, old_end) = self.node_mut(&indices).span();
+                let new_end = moved(old_end, old_end, delta);
+                if let Some(node) = reparse(&text[old_start..new_end], old_start, self.options)
+                {
+                    shift(&mut self.root, old_end, delta);
+                    *self.node_mut(&indices) = node;
+                    self.text = text;
+                    return Ok((old_start, new_end));
+                }
+                if indices.pop().is_none()
+                {
+                    break;
+                }
+            }
+        }
+
+        if let Err(error) = JsonParser::new(&text).with_options(self.options).validate()
+        {
+            return Err(EditError::InvalidText(format!("{:?}", error)));
+        }
+        self.root = build(&text, self.options);
+        self.text = text;
+        Ok((0, self.text.len()))
+    }
+
+    fn node_mut(&mut self, indices : &[usize]) -> &mut SyntaxNode
+    {
+        let mut node = &mut self.root;
+        for &i in indices
+        {
+            node = &mut node.children[i].value;
+        }
+        node
+    }
+}
+
+/// Parses `text`, found at `offset`, if it is a single value
+/// with nothing around it
+fn reparse(text : &str, offset : usize, options : ParserOptions) -> Option<SyntaxNode>
+{
+    JsonParser::new(text).with_options(options).validate().ok()?;
+    let mut node = build(text, options);
+    if node.span() != (0, text.len())
+    {
+        return None;
+    }
+    shift(&mut node, 0, offset as isize);
+    Some(node)
+}
+
+/// Moves every offset at or after `from` by `delta`
+fn shift(root : &mut SyntaxNode, from : usize, delta : isize)
+{
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop()
+    {
+        // nothing inside a value that ends before `from` moves
+        if node.end < from
+        {
+            continue;
+        }
+        node.start = moved(node.start, from, delta);
+        node.end = moved(node.end, from, delta);
+        for child in &mut node.children
+        {
+            child.start = moved(child.start, from, delta);
+            child.comma = child.comma.map(|comma| moved(comma, from, delta));
+            stack.push(&mut child.value);
+        }
+    }
+}
+
+fn moved(offset : usize, from : usize, delta : isize) -> usize
+{
+    if offset >= from {(offset as isize + delta) as usize} else {offset}
+}
+
+impl fmt::Display for SyntaxTree
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// The index a pointer segment names: digits without a leading zero
+fn array_index(segment : &str) -> Option<usize>
+{
+    let digits = !segment.is_empty() && segment.bytes().all(|b| b.is_ascii_digit());
+    if digits && (segment == "0" || !segment.starts_with('0')) {segment.parse().ok()} else {None}
+}
+
+/// Writes a key as a JSON string
+fn quote(key : &str) -> String
+{
+    let mut quoted = String::with_capacity(key.len() + 2);
+    quoted.push('"');
+    for c in key.chars()
+    {
+        match c
+        {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// An object or array whose children are being read
+struct Open
+{
+    node : SyntaxNode,
+    key : Option<(String, usize)>,
+    expect_key : bool,
+}
+
+/// Builds the nodes of a well-formed document
+fn build(text : &str, options : ParserOptions) -> SyntaxNode
+{
+    let mut lexer = Lex::new(text).with_options(options).keep_comments();
+    let offset = |token : &Token| token.span.as_ptr() as usize - text.as_ptr() as usize;
+    let mut stack : Vec<Open> = Vec::new();
+
+    loop
+    {
+        let token = lexer.next();
+        let start = offset(&token);
+        let end = start + token.span.len();
+
+        let expect_key = stack.last().map_or(false, |open| open.expect_key);
+        let kind = match token.token_type
+        {
+            TokenType::Comment | TokenType::Colon => continue,
+            TokenType::Comma =>
+            {
+                if let Some(open) = stack.last_mut()
+                {
+                    if let Some(child) = open.node.children.last_mut()
+                    {
+                        child.comma = Some(end);
+                    }
+                    open.expect_key = open.node.kind == NodeKind::Object;
+                }
+                continue;
+            },
+            TokenType::RightBrace | TokenType::RightBracket =>
+            {
+                let mut open = match stack.pop()
+                {
+                    Some(open) => open,
+                    None => unreachable!(),
+                };
+                open.node.end = end;
+                match add_child(&mut stack, open.node)
+                {
+                    Some(root) => return root,
+                    None => continue,
+                }
+            },
+            TokenType::End | TokenType::Error | TokenType::LongString => unreachable!(),
+
+            // a key, in any spelling the options allow
+            ref key_type if expect_key =>
+            {
+                let name = match *key_type
+                {
+                    TokenType::String(ref name) | TokenType::Identifier(ref name) => name.clone(),
+                    _ => token.span.to_owned(),
+                };
+                if let Some(open) = stack.last_mut()
+                {
+                    open.key = Some((name, start));
+                    open.expect_key = false;
+                }
+                continue;
+            },
+
+            TokenType::LeftBrace => NodeKind::Object,
+            TokenType::LeftBracket => NodeKind::Array,
+            TokenType::String(_) => NodeKind::String,
+            TokenType::Number(_) => NodeKind::Number,
+            TokenType::Bool(_) => NodeKind::Bool,
+            TokenType::Null | TokenType::Identifier(_) => NodeKind::Null,
+        };
+
+        let node = SyntaxNode{kind, start, end, children : Vec::new()};
+        if kind == NodeKind::Object || kind == NodeKind::Array
+        {
+            stack.push(Open{node, key : None, expect_key : kind == NodeKind::Object});
+        }
+        else if let Some(root) = add_child(&mut stack, node)
+        {
+            return root;
+        }
+    }
+}
+
+/// Adds a complete node to the innermost container,
+/// or returns it if it is the root
+fn add_child(stack : &mut [Open], value : SyntaxNode) -> Option<SyntaxNode>
+{
+    match stack.last_mut()
+    {
+        Some(open) =>
+        {
+            let (key, start) = match open.key.take()
+            {
+                Some((key, start)) => (Some(key), start),
+                None => (None, value.start),
+            };
+            open.node.children.push(Child{key, start, value, comma : None});
+            None
+        },
+        None => Some(value),
+    }
+}
@@ -0,0 +1,208 @@
+use json::JsonValue;
+use lex::{Token,TokenType};
+use parser::JsonParser;
+use parse_error::Result;
+use pointer::{JsonPointer,PathPattern,Step};
+use std::borrow::Cow;
+
+/// Extract the values matching `patterns` from a JSON document
+///
+/// The document is streamed through without building it; only
+/// matching values become `JsonValue`s, everything else is syntax
+/// checked and skipped without allocating. Results are returned in
+/// document order. Once every pattern is exact and has been found,
+/// the rest of the input is not read.
+pub fn extract<'src>(source : &'src str, patterns : &[PathPattern]) -> Result<'src, Vec<(JsonPointer, JsonValue)>>
+{
+    let mut extractor = Extractor
+    {
+        parser : JsonParser::new(source),
+        patterns,
+        found : vec![false; patterns.len()],
+        results : Vec::new(),
+    };
+
+    let mut path = Vec::new();
+    let token = extractor.parser.skip_token();
+    extractor.visit(token, &mut path)?;
+
+    if !extractor.is_complete()
+    {
+        match extractor.parser.skip_token()
+        {
+            Token{token_type: TokenType::End, ..} => {},
+            token => return Err(extractor.parser.error(token)),
+        }
+    }
+    Ok(extractor.results)
+}
+
+struct Extractor<'src, 'p>
+{
+    parser : JsonParser<'src>,
+    patterns : &'p [PathPattern],
+    found : Vec<bool>,
+    results : Vec<(JsonPointer, JsonValue)>,
+}
+
+impl<'src, 'p> Extractor<'src, 'p>
+{
+    /// True once nothing more can match - all patterns are exact and found
+    fn is_complete(&self) -> bool
+    {
+        self.patterns.iter().zip(&self.found).all(|(p, &found)| found && p.is_exact())
+    }
+
+    /// Visit the value starting with `token`, which was read without decoding strings
+    fn visit(&mut self, token : Token<'src>, path : &mut Vec<Step<'src>>) -> Result<'src, ()>
+    {
+        if self.patterns.iter().any(|p| p.matches_steps(path))
+        {
+            let value = self.parser.parse_value(self.parser.decode(token))?;
+            let index = self.results.len();
+            self.collect_below(&value, path);
+            self.record(index, path, value);
+            return Ok(());
+        }
+
+        if !self.patterns.iter().any(|p| p.matches_below(path))
+        {
+            return self.parser.skip_value(token);
+        }
+
+        match token.token_type
+        {
+            TokenType::LeftBrace => self.visit_object(path),
+            TokenType::LeftBracket => self.visit_array(path),
+            _ => self.parser.skip_value(token),
+        }
+    }
+
+    //OBJECT = '{' * '}'
+    //OBJECT = '{' * MEMBERS '}'
+    fn visit_object(&mut self, path : &mut Vec<Step<'src>>) -> Result<'src, ()>
+    {
+        let mut token = self.parser.skip_token();
+        if let TokenType::RightBrace = token.token_type
+        {
+            return Ok(());
+        }
+
+        loop
+        {
+            let key = match token
+            {
+                Token{token_type: TokenType::String(_), span} => self.parser.unescape(span)?,
+                token => return Err(self.parser.error(token)),
+            };
+            match self.parser.skip_token()
+            {
+                Token{token_type: TokenType::Colon, ..} => {},
+                token => return Err(self.parser.error(token)),
+            }
+
+            path.push(Step::Key(key));
+            let value = self.parser.skip_token();
+            self.visit(value, path)?;
+            path.pop();
+
+            if self.is_complete()
+            {
+                return Ok(());
+            }
+
+            match self.parser.skip_token()
+            {
+                Token{token_type: TokenType::Comma, ..} => token = self.parser.skip_token(),
+                Token{token_type: TokenType::RightBrace, ..} => return Ok(()),
+                token => return Err(self.parser.error(token)),
+            }
+        }
+    }
+
+    //ARRAY = '[' * ']'
+    //ARRAY = '[' * ELEMENTS ']'
+    fn visit_array(&mut self, path : &mut Vec<Step<'src>>) -> Result<'src, ()>
+    {
+        let mut token = self.parser.skip_token();
+        if let TokenType::RightBracket = token.token_type
+        {
+            return Ok(());
+        }
+
+        let mut index = 0;
+        loop
+        {
+            path.push(Step::Index(index));
+            self.visit(token, path)?;
+            path.pop();
+            index += 1;
+
+            if self.is_complete()
+            {
+                return Ok(());
+            }
+
+            match self.parser.skip_token()
+            {
+                Token{token_type: TokenType::Comma, ..} => token = self.parser.skip_token(),
+                Token{token_type: TokenType::RightBracket, ..} => return Ok(()),
+                token => return Err(self.parser.error(token)),
+            }
+        }
+    }
+
+    /// Collect matches nested inside a value that was itself matched and built
+    fn collect_below(&mut self, value : &JsonValue, path : &mut Vec<Step<'src>>)
+    {
+        if !self.patterns.iter().any(|p| p.matches_below(path))
+        {
+            return;
+        }
+
+        match *value
+        {
+            JsonValue::JsonObject(ref object) =>
+            {
+                for (key, child) in object
+                {
+                    path.push(Step::Key(Cow::Owned(key.clone())));
+                    self.collect_child(child, path);
+                    path.pop();
+                }
+            },
+            JsonValue::JsonArray(ref array) =>
+            {
+                for (index, child) in array.iter().enumerate()
+                {
+                    path.push(Step::Index(index));
+                    self.collect_child(child, path);
+                    path.pop();
+                }
+            },
+            _ => {},
+        }
+    }
+
+    fn collect_child(&mut self, child : &JsonValue, path : &mut Vec<Step<'src>>)
+    {
+        let index = self.results.len();
+        self.collect_below(child, path);
+        if self.patterns.iter().any(|p| p.matches_steps(path))
+        {
+            self.record(index, path, child.clone());
+        }
+    }
+
+    fn record(&mut self, index : usize, path : &[Step], value : JsonValue)
+    {
+        for (pattern, found) in self.patterns.iter().zip(self.found.iter_mut())
+        {
+            if pattern.matches_steps(path)
+            {
+                *found = true;
+            }
+        }
+        self.results.insert(index, (JsonPointer::from_steps(path), value));
+    }
+}
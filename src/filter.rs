@@ -0,0 +1,45 @@
+use pointer::{JsonPointer,PathPattern,Step};
+
+/// Decides which object members are kept while parsing
+///
+/// Excluded members are syntax checked but never built.
+/// Array elements are always kept, so indices stay meaningful;
+/// the filter applies to the members nested inside them.
+pub enum FieldFilter
+{
+    /// Keep only members matching a pattern, their subtrees
+    /// and the members leading to them
+    Allow(Vec<PathPattern>),
+    /// Drop members matching a pattern
+    Deny(Vec<PathPattern>),
+    /// Keep a member if the callback returns true for its path
+    Callback(Box<dyn Fn(&JsonPointer) -> bool>),
+}
+
+impl FieldFilter
+{
+    /// True if nothing below `path` can be excluded
+    pub(crate) fn keeps_subtree(&self, path : &[Step]) -> bool
+    {
+        match *self
+        {
+            FieldFilter::Allow(ref patterns) => patterns.iter().any(|p| p.matches_steps(path)),
+            FieldFilter::Deny(ref patterns) => !patterns.iter().any(|p| p.matches_below(path)),
+            FieldFilter::Callback(_) => false,
+        }
+    }
+
+    /// True if the member at `path` is kept
+    pub(crate) fn keeps_member(&self, path : &[Step]) -> bool
+    {
+        match *self
+        {
+            FieldFilter::Allow(ref patterns) =>
+            {
+                patterns.iter().any(|p| p.matches_steps(path) || p.matches_below(path))
+            },
+            FieldFilter::Deny(ref patterns) => !patterns.iter().any(|p| p.matches_steps(path)),
+            FieldFilter::Callback(ref keep) => keep(&JsonPointer::from_steps(path)),
+        }
+    }
+}
@@ -0,0 +1,346 @@
+use json::{self,JsonValue};
+use pointer::JsonPointer;
+use std::{f64, fmt, slice, vec};
+
+/// A hash function that fingerprints are computed with
+///
+/// Each value is hashed with a fresh digest, so its fingerprint does not
+/// depend on where it appears.
+pub trait Digest : Default
+{
+    fn update(&mut self, bytes : &[u8]);
+    fn finish(self) -> Vec<u8>;
+}
+
+/// The digest of a value's structure and content
+///
+/// Key order, formatting and the spelling of numbers and escapes do not
+/// change it: `{"a": 1.0, "b": "A"}` and `{"b":"A","a":1}` have
+/// the same fingerprint. Negative zero counts as zero and every NaN as
+/// the same value.
+#[derive(Debug,Clone,PartialEq,Eq,Hash,PartialOrd,Ord)]
+pub struct Fingerprint(Vec<u8>);
+
+impl Fingerprint
+{
+    pub fn as_bytes(&self) -> &[u8]
+    {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for Fingerprint
+{
+    fn from(bytes : Vec<u8>) -> Fingerprint
+    {
+        Fingerprint(bytes)
+    }
+}
+
+/// Lowercase hexadecimal
+impl fmt::Display for Fingerprint
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for byte in &self.0
+        {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/// The fingerprint of `value` using digest `D`, such as `Sha256`
+pub fn fingerprint<D : Digest>(value : &JsonValue) -> Fingerprint
+{
+    walk::<D, _>(value, None, &mut |_, _| {})
+}
+
+/// The fingerprint of every value in `value`, with its path
+///
+/// Values come after everything they contain, so the root is last.
+/// Equal fingerprints across documents mark identical fragments.
+pub fn subtree_fingerprints<D : Digest>(value : &JsonValue) -> Vec<(JsonPointer, Fingerprint)>
+{
+    let mut fingerprints = Vec::new();
+    walk::<D, _>(value, Some(JsonPointer::root()), &mut |path, fingerprint|
+    {
+        fingerprints.push((path.cloned().unwrap_or_else(JsonPointer::root), fingerprint.clone()));
+    });
+    fingerprints
+}
+
+/// The members, ordered by key, or elements of a container being hashed
+enum Children<'a>
+{
+    Object(vec::IntoIter<(&'a String, &'a JsonValue)>),
+    Array(slice::Iter<'a, JsonValue>, usize),
+}
+
+/// Hashes `value` bottom up, calling `visit` with each fingerprint
+///
+/// A container's digest takes its members' keys and fingerprints, so the
+/// stack of open containers holds one running digest each. Paths are
+/// only tracked when `path` is given.
+fn walk<D : Digest, F>(value : &JsonValue, mut path : Option<JsonPointer>, visit : &mut F) -> Fingerprint
+    where F : FnMut(Option<&JsonPointer>, &Fingerprint)
+{
+    let mut stack : Vec<(Children, D)> = Vec::new();
+    let mut next = Some(value);
+    loop
+    {
+        // a finished value, and whether it was already visited
+        let (fingerprint, visited) = match next.take()
+        {
+            Some(&JsonValue::JsonRaw(ref raw)) => match raw.parse()
+            {
+                // hashed as the value it holds, whose walk visits its own
+                // fragments; raw text is always a single value
+                Ok(parsed) =>
+                {
+                    let fingerprint = walk::<D, F>(&parsed, path.clone(), visit);
+                    json::drop_nested(parsed);
+                    (fingerprint, true)
+                },
+                // text that is not a value is hashed as it is, under a tag
+                // of its own so it matches no value
+                Err(_) =>
+                {
+                    let mut digest = D::default();
+                    digest.update(b"`");
+                    digest.update(raw.as_str().as_bytes());
+                    (Fingerprint(digest.finish()), false)
+                },
+            },
+            Some(value) =>
+            {
+                let mut digest = D::default();
+                match *value
+                {
+                    JsonValue::JsonObject(ref object) =>
+                    {
+                        let mut members : Vec<_> = object.iter().collect();
+                        members.sort_by(|a, b| a.0.cmp(b.0));
+                        digest.update(b"{");
+                        stack.push((Children::Object(members.into_iter()), digest));
+                        continue;
+                    },
+                    JsonValue::JsonArray(ref array) =>
+                    {
+                        digest.update(b"[");
+                        stack.push((Children::Array(array.iter(), 0), digest));
+                        continue;
+                    },
+                    JsonValue::JsonString(ref string) =>
+                    {
+                        digest.update(b"\"");
+                        digest.update(string.as_bytes());
+                    },
+                    JsonValue::JsonNumber(number) =>
+                    {
+                        let number = if number == 0.0 {0.0} else if number.is_nan() {f64::NAN} else {number};
+                        digest.update(b"#");
+                        digest.update(&number.to_bits().to_be_bytes());
+                    },
+                    JsonValue::JsonBool(b) => digest.update(if b {b"true"} else {b"false"}),
+                    JsonValue::Null => digest.update(b"null"),
+                    JsonValue::JsonRaw(_) => unreachable!(),
+                }
+                (Fingerprint(digest.finish()), false)
+            },
+            None =>
+            {
+                let (children, digest) = match stack.last_mut()
+                {
+                    Some(&mut (ref mut children, ref mut digest)) => (children, digest),
+                    None => unreachable!(),
+                };
+                let segment = match *children
+                {
+                    Children::Object(ref mut members) => members.next().map(|(key, value)|
+                    {
+                        // length-prefixed, so a key cannot run into the fingerprint after it
+                        digest.update(&(key.len() as u64).to_be_bytes());
+                        digest.update(key.as_bytes());
+                        next = Some(value);
+                        key.clone()
+                    }),
+                    Children::Array(ref mut elements, ref mut index) => elements.next().map(|value|
+                    {
+                        next = Some(value);
+                        *index += 1;
+                        (*index - 1).to_string()
+                    }),
+                };
+                if let Some(segment) = segment
+                {
+                    if let Some(ref mut path) = path
+                    {
+                        path.push(&segment);
+                    }
+                    continue;
+                }
+                match stack.pop()
+                {
+                    Some((_, digest)) => (Fingerprint(digest.finish()), false),
+                    None => unreachable!(),
+                }
+            },
+        };
+
+        if !visited
+        {
+            visit(path.as_ref(), &fingerprint);
+        }
+        match stack.last_mut()
+        {
+            None => return fingerprint,
+            Some(&mut (_, ref mut digest)) => digest.update(fingerprint.as_bytes()),
+        }
+        if let Some(ref mut path) = path
+        {
+            path.pop();
+        }
+    }
+}
+
+/// SHA-256 (FIPS 180-4)
+#[derive(Clone)]
+pub struct Sha256
+{
+    state : [u32; 8],
+    block : [u8; 64],
+    /// Bytes waiting in `block`
+    buffered : usize,
+    /// Bytes hashed so far
+    length : u64,
+}
+
+const SHA256_ROUNDS : [u32; 64] =
+[
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+impl Default for Sha256
+{
+    fn default() -> Sha256
+    {
+        Sha256
+        {
+            state : [0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19],
+            block : [0; 64],
+            buffered : 0,
+            length : 0,
+        }
+    }
+}
+
+impl Sha256
+{
+    fn compress(&mut self)
+    {
+        let mut schedule = [0u32; 64];
+        for (i, word) in self.block.chunks(4).enumerate()
+        {
+            schedule[i] = u32::from(word[0]) << 24 | u32::from(word[1]) << 16 | u32::from(word[2]) << 8 | u32::from(word[3]);
+        }
+        for i in 16..64
+        {
+            let s0 = schedule[i - 15].rotate_right(7) ^ schedule[i - 15].rotate_right(18) ^ (schedule[i - 15] >> 3);
+            let s1 = schedule[i - 2].rotate_right(17) ^ schedule[i - 2].rotate_right(19) ^ (schedule[i - 2] >> 10);
+            schedule[i] = schedule[i - 16].wrapping_add(s0).wrapping_add(schedule[i - 7]).wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = self.state;
+        for i in 0..64
+        {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let choice = (e & f) ^ (!e & g);
+            let t1 = h.wrapping_add(s1).wrapping_add(choice).wrapping_add(SHA256_ROUNDS[i]).wrapping_add(schedule[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let majority = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(majority);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (word, added) in self.state.iter_mut().zip(&[a, b, c, d, e, f, g, h])
+        {
+            *word = word.wrapping_add(*added);
+        }
+    }
+}
+
+impl Digest for Sha256
+{
+    fn update(&mut self, mut bytes : &[u8])
+    {
+        self.length += bytes.len() as u64;
+        while !bytes.is_empty()
+        {
+            let count = bytes.len().min(64 - self.buffered);
+            self.block[self.buffered..self.buffered + count].copy_from_slice(&bytes[..count]);
+            self.buffered += count;
+            bytes = &bytes[count..];
+            if self.buffered == 64
+            {
+                self.compress();
+                self.buffered = 0;
+            }
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8>
+    {
+        let bits = self.length.wrapping_mul(8);
+        self.update(&[0x80]);
+        while self.buffered != 56
+        {
+            self.update(&[0]);
+        }
+        self.update(&bits.to_be_bytes());
+        self.state.iter().flat_map(|word| word.to_be_bytes().to_vec()).collect()
+    }
+}
+
+/// 64-bit FNV-1a, fast but not collision resistant
+#[derive(Debug,Clone)]
+pub struct Fnv1a64
+{
+    state : u64,
+}
+
+impl Default for Fnv1a64
+{
+    fn default() -> Fnv1a64
+    {
+        Fnv1a64{state : 0xcbf2_9ce4_8422_2325}
+    }
+}
+
+impl Digest for Fnv1a64
+{
+    fn update(&mut self, bytes : &[u8])
+    {
+        for &byte in bytes
+        {
+            self.state = (self.state ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(self) -> Vec<u8>
+    {
+        self.state.to_be_bytes().to_vec()
+    }
+}
@@ -0,0 +1,355 @@
+use json::{self,JsonValue};
+use lex::{Lex,TokenType};
+use parse_error::Result;
+use parser::JsonParser;
+use pointer::JsonPointer;
+use raw::RawValue;
+use std::collections::HashSet;
+use std::fmt;
+
+/// The largest magnitude of an integer that I-JSON allows, 2^53 - 1
+const MAX_SAFE_INTEGER : f64 = 9_007_199_254_740_991.0;
+
+/// A way in which a document is not I-JSON (RFC 7493)
+#[derive(Debug,Clone,PartialEq)]
+pub enum ViolationKind
+{
+    /// The object already has a member with this key
+    DuplicateKey(String),
+    /// A `\uXXXX` escape of a surrogate that is not part of a pair
+    LoneSurrogate(u16),
+    /// A Unicode noncharacter, such as U+FFFF, in a string or key
+    Noncharacter(char),
+    /// A number, as written, that an IEEE double cannot hold exactly
+    /// to its precision, or that overflows or underflows a double
+    ImpreciseNumber(String),
+    /// An integer beyond ±(2^53 - 1)
+    IntegerOutOfRange(f64),
+    /// NaN or an infinity, which JSON cannot represent
+    NonFiniteNumber(f64),
+}
+
+/// A single violation of I-JSON and where it occurs
+#[derive(Debug,Clone,PartialEq)]
+pub struct Violation
+{
+    /// The value, or for a key the member, at fault
+    pub path : JsonPointer,
+    pub kind : ViolationKind,
+    /// Byte offset of the token at fault, when checking source text
+    pub offset : Option<usize>,
+}
+
+impl fmt::Display for ViolationKind
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self
+        {
+            ViolationKind::DuplicateKey(ref key) => write!(f, "duplicate key {:?}", key),
+            ViolationKind::LoneSurrogate(unit) => write!(f, "lone surrogate \\u{:04X}", unit),
+            ViolationKind::Noncharacter(c) => write!(f, "noncharacter U+{:04X}", c as u32),
+            ViolationKind::ImpreciseNumber(ref number) => write!(f, "number {} is not representable as a double", number),
+            ViolationKind::IntegerOutOfRange(number) => write!(f, "integer {} is beyond 2^53 - 1", number),
+            ViolationKind::NonFiniteNumber(number) => write!(f, "non-finite number {}", number),
+        }
+    }
+}
+
+impl fmt::Display for Violation
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at {:?}", self.kind, self.path.to_string())?;
+        if let Some(offset) = self.offset
+        {
+            write!(f, " (byte {})", offset)?;
+        }
+        Ok(())
+    }
+}
+
+/// Check that `source` is an I-JSON text
+///
+/// Fails if `source` is not JSON at all, otherwise lists every violation
+/// in source order. Unlike a parsed `JsonValue`, the source still shows
+/// duplicate keys, lone surrogate escapes and the digits of each number.
+pub fn check_source<'src>(source : &'src str) -> Result<'src, Vec<Violation>>
+{
+    JsonParser::new(source).validate()?;
+
+    let mut lexer = Lex::new(source);
+    let mut violations = Vec::new();
+    // keys seen in each open object, or None for an array and its next index
+    let mut stack : Vec<(Option<HashSet<String>>, usize)> = Vec::new();
+    let mut path = JsonPointer::root();
+    let mut expect_key = false;
+
+    loop
+    {
+        let token = lexer.next();
+        let offset = token.span.as_ptr() as usize - source.as_ptr() as usize;
+        let mut report = |path : &JsonPointer, kind| violations.push(Violation{path : path.clone(), kind, offset : Some(offset)});
+
+        let starts_value = match token.token_type
+        {
+            TokenType::String(_) if expect_key => false,
+            TokenType::String(_) | TokenType::Number(_) | TokenType::Bool(_) | TokenType::Null => true,
+            TokenType::LeftBrace | TokenType::LeftBracket => true,
+            _ => false,
+        };
+        if starts_value
+        {
+            // every value in an array is an element
+            if let Some(&mut (None, ref mut index)) = stack.last_mut()
+            {
+                path.push(&index.to_string());
+                *index += 1;
+            }
+        }
+
+        match token.token_type
+        {
+            TokenType::String(ref key) if expect_key =>
+            {
+                path.push(key);
+                check_escapes(token.span, &path, &mut report);
+                check_characters(key, &path, &mut report);
+                if let Some(&mut (Some(ref mut keys), _)) = stack.last_mut()
+                {
+                    if !keys.insert(key.clone())
+                    {
+                        report(&path, ViolationKind::DuplicateKey(key.clone()));
+                    }
+                }
+                expect_key = false;
+            },
+            TokenType::Colon => {},
+            TokenType::Comma => expect_key = match stack.last() {Some(&(Some(_), _)) => true, _ => false},
+            TokenType::LeftBrace =>
+            {
+                stack.push((Some(HashSet::new()), 0));
+                expect_key = true;
+            },
+            TokenType::LeftBracket => stack.push((None, 0)),
+            TokenType::RightBrace | TokenType::RightBracket =>
+            {
+                stack.pop();
+                expect_key = false;
+                leave_value(&mut path, &stack);
+            },
+            TokenType::String(ref string) =>
+            {
+                check_escapes(token.span, &path, &mut report);
+                check_characters(string, &path, &mut report);
+                leave_value(&mut path, &stack);
+            },
+            TokenType::Number(_) =>
+            {
+                let number = token.span.parse::<f64>().unwrap_or(0.0);
+                if !is_precise(token.span, number)
+                {
+                    report(&path, ViolationKind::ImpreciseNumber(token.span.to_owned()));
+                }
+                else if is_out_of_range(number)
+                {
+                    report(&path, ViolationKind::IntegerOutOfRange(number));
+                }
+                leave_value(&mut path, &stack);
+            },
+            TokenType::Bool(_) | TokenType::Null => leave_value(&mut path, &stack),
+            _ => break,
+        }
+    }
+    Ok(violations)
+}
+
+/// Step out of the member or element whose value was just read
+fn leave_value(path : &mut JsonPointer, stack : &[(Option<HashSet<String>>, usize)])
+{
+    if !stack.is_empty()
+    {
+        path.pop();
+    }
+}
+
+/// Check that `value` can be written as an I-JSON text
+///
+/// A `JsonValue` cannot hold duplicate keys or lone surrogates, so only
+/// noncharacters, integer ranges and non-finite numbers are checked,
+/// except in raw values, whose text is checked like `check_source` does
+pub fn check_value(value : &JsonValue) -> Vec<Violation>
+{
+    let mut violations = Vec::new();
+    let mut stack = vec![(JsonPointer::root(), value)];
+    while let Some((path, value)) = stack.pop()
+    {
+        let mut report = |path : &JsonPointer, kind| violations.push(Violation{path : path.clone(), kind, offset : None});
+        match *value
+        {
+            JsonValue::JsonObject(ref object) =>
+            {
+                // members are pushed in reverse, to be checked in order
+                for (key, member) in object.iter().rev()
+                {
+                    let mut member_path = path.clone();
+                    member_path.push(key);
+                    check_characters(key, &member_path, &mut report);
+                    stack.push((member_path, member));
+                }
+            },
+            JsonValue::JsonArray(ref array) =>
+            {
+                for (index, element) in array.iter().enumerate().rev()
+                {
+                    let mut element_path = path.clone();
+                    element_path.push(&index.to_string());
+                    stack.push((element_path, element));
+                }
+            },
+            JsonValue::JsonString(ref string) => check_characters(string, &path, &mut report),
+            JsonValue::JsonNumber(number) if !number.is_finite() =>
+            {
+                report(&path, ViolationKind::NonFiniteNumber(number));
+            },
+            JsonValue::JsonNumber(number) if is_out_of_range(number) =>
+            {
+                report(&path, ViolationKind::IntegerOutOfRange(number));
+            },
+            JsonValue::JsonRaw(ref raw) =>
+            {
+                for violation in check_raw(raw)
+                {
+                    let mut raw_path = path.clone();
+                    for segment in violation.path.segments()
+                    {
+                        raw_path.push(segment);
+                    }
+                    report(&raw_path, violation.kind);
+                }
+            },
+            _ => {},
+        }
+    }
+    violations
+}
+
+/// The violations inside a raw value, with paths relative to it
+///
+/// Strict JSON text is checked as source, anything else as the value
+/// it is written as
+fn check_raw(raw : &RawValue) -> Vec<Violation>
+{
+    if raw.is_strict()
+    {
+        return check_source(raw.as_str()).unwrap_or_default();
+    }
+    match raw.parse()
+    {
+        Ok(parsed) =>
+        {
+            let violations = check_value(&parsed);
+            json::drop_nested(parsed);
+            violations
+        },
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Reports unpaired surrogates among the `\uXXXX` escapes of a string token
+fn check_escapes<F>(span : &str, path : &JsonPointer, report : &mut F)
+    where F : FnMut(&JsonPointer, ViolationKind)
+{
+    if !span.contains("\\u")
+    {
+        return;
+    }
+
+    // the escaped units, and whether each directly follows the previous one
+    let mut units : Vec<(u16, bool)> = Vec::new();
+    let bytes = span.as_bytes();
+    let mut i = 0;
+    let mut last_end = 0;
+    while i < bytes.len()
+    {
+        if bytes[i] != b'\\'
+        {
+            i += 1;
+            continue;
+        }
+        if bytes.get(i + 1) == Some(&b'u')
+        {
+            if let Some(unit) = span.get(i + 2..i + 6).and_then(|hex| u16::from_str_radix(hex, 16).ok())
+            {
+                units.push((unit, last_end == i && !units.is_empty()));
+                last_end = i + 6;
+                i += 6;
+                continue;
+            }
+        }
+        i += 2;
+    }
+
+    let mut i = 0;
+    while i < units.len()
+    {
+        let (unit, _) = units[i];
+        match unit
+        {
+            0xD800..=0xDBFF => match units.get(i + 1)
+            {
+                Some(&(0xDC00..=0xDFFF, true)) => i += 1,
+                _ => report(path, ViolationKind::LoneSurrogate(unit)),
+            },
+            0xDC00..=0xDFFF => report(path, ViolationKind::LoneSurrogate(unit)),
+            _ => {},
+        }
+        i += 1;
+    }
+}
+
+/// Reports the noncharacters in a decoded string or key
+fn check_characters<F>(string : &str, path : &JsonPointer, report : &mut F)
+    where F : FnMut(&JsonPointer, ViolationKind)
+{
+    for c in string.chars()
+    {
+        let code = c as u32;
+        if (0xFDD0..=0xFDEF).contains(&code) || code & 0xFFFE == 0xFFFE
+        {
+            report(path, ViolationKind::Noncharacter(c));
+        }
+    }
+}
+
+fn is_out_of_range(number : f64) -> bool
+{
+    number.fract() == 0.0 && number.abs() > MAX_SAFE_INTEGER
+}
+
+/// True if the double nearest to the number written as `text` has the same
+/// decimal value, once both are reduced to their significant digits
+fn is_precise(text : &str, number : f64) -> bool
+{
+    number.is_finite() && significant(text) == significant(&format!("{:e}", number))
+}
+
+/// The significant digits of a decimal number and the exponent of the
+/// first of them, or None for zero
+fn significant(text : &str) -> Option<(String, i64)>
+{
+    let text = text.trim_start_matches('-');
+    let (mantissa, exponent) = match text.find(&['e', 'E'][..])
+    {
+        Some(e) => (&text[..e], text[e + 1..].parse::<i64>().unwrap_or(0)),
+        None => (text, 0),
+    };
+    let point = mantissa.find('.').unwrap_or(mantissa.len());
+    let digits : String = mantissa.chars().filter(|&c| c != '.').collect();
+
+    let leading = digits.len() - digits.trim_start_matches('0').len();
+    let digits = digits.trim_matches('0');
+    if digits.is_empty()
+    {
+        return None;
+    }
+    Some((digits.to_owned(), exponent + point as i64 - leading as i64))
+}
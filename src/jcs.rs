@@ -0,0 +1,205 @@
+use json::{self,JsonValue};
+use ser::{self,Escaping,SerializeError};
+use std::fmt::Write;
+use std::{slice, vec};
+
+/// Write `value` in the JSON Canonicalization Scheme (RFC 8785)
+///
+/// Equal values always give the same text, byte for byte: no whitespace,
+/// members ordered by the UTF-16 code units of their keys, numbers as
+/// ECMAScript writes them and strings with only the escapes JSON needs.
+/// Raw values are parsed and written canonically too. Fails on NaN and
+/// infinities, which the scheme cannot represent.
+pub fn to_string(value : &JsonValue) -> Result<String, SerializeError>
+{
+    let mut out = String::new();
+    write(value, &mut out)?;
+    Ok(out)
+}
+
+/// An object or array being written, and the members or elements left
+enum Open<'a>
+{
+    Object(vec::IntoIter<(&'a String, &'a JsonValue)>),
+    Array(slice::Iter<'a, JsonValue>),
+}
+
+/// Write `value` canonically to `out`, see `to_string`
+///
+/// Nested values are kept on an explicit stack, so any depth can be written
+pub fn write<W : Write>(value : &JsonValue, out : &mut W) -> Result<(), SerializeError>
+{
+    // open containers, and whether they have written a member or element yet
+    let mut stack : Vec<(Open, bool)> = Vec::new();
+    let mut next = Some(value);
+    loop
+    {
+        if let Some(value) = next.take()
+        {
+            match *value
+            {
+                JsonValue::JsonObject(ref object) =>
+                {
+                    let mut members : Vec<_> = object.iter().collect();
+                    members.sort_by(|a, b| a.0.encode_utf16().cmp(b.0.encode_utf16()));
+                    out.write_char('{')?;
+                    stack.push((Open::Object(members.into_iter()), false));
+                },
+                JsonValue::JsonArray(ref array) =>
+                {
+                    out.write_char('[')?;
+                    stack.push((Open::Array(array.iter()), false));
+                },
+                JsonValue::JsonString(ref string) => ser::write_string(string, Escaping::minimal(), out)?,
+                JsonValue::JsonNumber(number) => write_number(number, out)?,
+                JsonValue::JsonBool(b) => write!(out, "{}", b)?,
+                JsonValue::Null => out.write_str("null")?,
+                JsonValue::JsonRaw(ref raw) =>
+                {
+                    // the parsed text holds no raw values, so this goes one level deep at most
+                    let parsed = raw.parse().map_err(|error| SerializeError::InvalidFragment(format!("{:?}", error)))?;
+                    let result = write(&parsed, out);
+                    json::drop_nested(parsed);
+                    result?;
+                },
+            }
+        }
+
+        let (open, started) = match stack.last_mut()
+        {
+            None => return Ok(()),
+            Some(&mut (ref mut open, ref mut started)) => (open, started),
+        };
+
+        let close = match *open
+        {
+            Open::Object(ref mut members) => match members.next()
+            {
+                Some((key, value)) =>
+                {
+                    if *started
+                    {
+                        out.write_char(',')?;
+                    }
+                    ser::write_string(key, Escaping::minimal(), out)?;
+                    out.write_char(':')?;
+                    next = Some(value);
+                    *started = true;
+                    continue;
+                },
+                None => '}',
+            },
+            Open::Array(ref mut elements) => match elements.next()
+            {
+                Some(value) =>
+                {
+                    if *started
+                    {
+                        out.write_char(',')?;
+                    }
+                    next = Some(value);
+                    *started = true;
+                    continue;
+                },
+                None => ']',
+            },
+        };
+        stack.pop();
+        out.write_char(close)?;
+    }
+}
+
+/// Writes a number as ECMAScript's `Number.prototype.toString` does
+///
+/// The digits are the shortest that read back as the same number; plain
+/// notation is used from 1e-6 up to 1e21, and outside it an exponent
+/// with an explicit sign. Negative zero is written `0`.
+fn write_number<W : Write>(number : f64, out : &mut W) -> Result<(), SerializeError>
+{
+    if !number.is_finite()
+    {
+        return Err(SerializeError::NonFiniteNumber(number));
+    }
+    if number == 0.0
+    {
+        out.write_char('0')?;
+        return Ok(());
+    }
+    if number < 0.0
+    {
+        out.write_char('-')?;
+    }
+    let (digits, exponent) = shortest_digits(number.abs());
+    let write_digits = |digits : &[u8], out : &mut W| digits.iter().try_for_each(|&digit| out.write_char(char::from(digit)));
+    let zeros = |count : i32, out : &mut W| (0..count).try_for_each(|_| out.write_char('0'));
+
+    // the decimal point goes after `point` digits
+    let length = digits.len() as i32;
+    let point = exponent + 1;
+    if length <= point && point <= 21
+    {
+        write_digits(&digits, out)?;
+        zeros(point - length, out)?;
+    }
+    else if 0 < point && point <= 21
+    {
+        write_digits(&digits[..point as usize], out)?;
+        out.write_char('.')?;
+        write_digits(&digits[point as usize..], out)?;
+    }
+    else if -6 < point && point <= 0
+    {
+        out.write_str("0.")?;
+        zeros(-point, out)?;
+        write_digits(&digits, out)?;
+    }
+    else
+    {
+        write_digits(&digits[..1], out)?;
+        if length > 1
+        {
+            out.write_char('.')?;
+            write_digits(&digits[1..], out)?;
+        }
+        write!(out, "e{}{}", if exponent < 0 {"-"} else {"+"}, exponent.abs())?;
+    }
+    Ok(())
+}
+
+/// The shortest round-trip digits of a positive number, as ASCII, and the
+/// power of ten of the first
+///
+/// When the number lies exactly halfway between two shortest candidates
+/// Rust takes the larger, where ECMAScript takes the one ending in an even
+/// digit.
+fn shortest_digits(magnitude : f64) -> (Vec<u8>, i32)
+{
+    let (mut digits, exponent) = scientific(&format!("{:e}", magnitude));
+    let last = digits.len() - 1;
+    // a tie needs the exact value to have one more digit, a 5; a cheap
+    // rounding to that many digits rules most numbers out first
+    let (rounded, _) = scientific(&format!("{:.*e}", digits.len(), magnitude));
+    if digits[last] % 2 == 1 && rounded[..last] == digits[..last] && rounded[last + 1] == b'5'
+    {
+        // a double has at most 767 significant digits, so this is exact
+        let (exact, exact_exponent) = scientific(&format!("{:.800e}", magnitude));
+        let tie = exact_exponent == exponent && exact[..last + 2] == rounded[..] && exact[last + 2..].iter().all(|&digit| digit == b'0');
+        if tie && exact[last] + 1 == digits[last]
+        {
+            digits[last] -= 1;
+        }
+        else if tie && exact[last] == digits[last] && digits[last] < b'9'
+        {
+            digits[last] += 1;
+        }
+    }
+    (digits, exponent)
+}
+
+/// Splits Rust's `{:e}` text into its digits and exponent
+fn scientific(text : &str) -> (Vec<u8>, i32)
+{
+    let mut parts = text.splitn(2, 'e');
+    let digits = parts.next().unwrap_or("").bytes().filter(|&b| b != b'.').collect();
+    (digits, parts.next().and_then(|exponent| exponent.parse().ok()).unwrap_or(0))
+}
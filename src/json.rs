@@ -1,13 +1,21 @@
-use std::collections::HashMap;
+use map::{Entry,JsonMap};
+use raw::RawValue;
+use std::cmp::Ordering;
+use std::convert::TryFrom;
 use std::error::Error;
 use std::fmt;
 
-pub type JsonObject = HashMap<String, JsonValue>;
+/// An object, its members in the order they were inserted or parsed
+pub type JsonObject = JsonMap<JsonValue>;
 pub type JsonArray = Vec<JsonValue>;
 
+/// An owned object, known to be one, see `JsonValue::into_object`
+#[derive(Debug,Clone,Default,PartialEq)]
 pub struct JsonObjectContainer(JsonObject);
 pub struct JsonObjectContainerRef<'a>(&'a JsonObject);
 pub struct JsonObjectContainerMutRef<'a>(&'a mut JsonObject);
+/// An owned array, known to be one, see `JsonValue::into_array`
+#[derive(Debug,Clone,Default,PartialEq)]
 pub struct JsonArrayContainer(JsonArray);
 pub struct JsonArrayContainerRef<'a>(&'a JsonArray);
 pub struct JsonArrayContainerMutRef<'a>(&'a mut JsonArray);
@@ -29,7 +37,7 @@ impl<'a>  Error for InvalidValueError<'a>
     }
 }
 
-#[derive(PartialEq,Debug)]
+#[derive(PartialEq,Debug,Clone)]
 pub enum JsonValue
 {
     JsonObject(JsonObject),
@@ -38,6 +46,24 @@ pub enum JsonValue
     JsonString(String),
     JsonBool(bool),
     Null,
+    /// A value captured unparsed, see `RawCapture`
+    JsonRaw(RawValue),
+}
+
+/// Drops `value` iteratively, so tearing down a deeply nested document
+/// cannot overflow the stack, as dropping it in place would
+pub(crate) fn drop_nested(value : JsonValue)
+{
+    let mut stack = vec![value];
+    while let Some(mut value) = stack.pop()
+    {
+        match value
+        {
+            JsonValue::JsonObject(ref mut object) => stack.extend(object.drain().map(|(_, value)| value)),
+            JsonValue::JsonArray(ref mut array) => stack.append(array),
+            _ => {},
+        }
+    }
 }
 
 impl JsonValue {
@@ -89,6 +115,14 @@ impl JsonValue {
             _ => false,
         }
     }
+    pub fn is_raw(&self) -> bool
+    {
+        match *self
+        {
+            JsonValue::JsonRaw(_) => true,
+            _ => false,
+        }
+    }
 
     fn desc(&self) -> &'static str
     {
@@ -100,6 +134,7 @@ impl JsonValue {
             JsonValue::JsonString(_) => "JsonString",
             JsonValue::JsonBool(_) => "JsonBool",
             JsonValue::Null => "Null",
+            JsonValue::JsonRaw(_) => "JsonRaw",
         }
     }
 
@@ -192,6 +227,35 @@ impl JsonValue {
             value => Err(InvalidValueError{value}),
         }
     }
+
+    pub fn get_raw_ref<'a>(&'a self) -> Result<&'a RawValue, InvalidValueError<'a>>
+    {
+        match self
+        {
+            JsonValue::JsonRaw(ref raw) => Ok(raw),
+            value => Err(InvalidValueError{value}),
+        }
+    }
+
+    /// Take the object out of the value, or give the value back
+    pub fn into_object(self) -> Result<JsonObjectContainer, JsonValue>
+    {
+        match self
+        {
+            JsonValue::JsonObject(object) => Ok(JsonObjectContainer(object)),
+            value => Err(value),
+        }
+    }
+
+    /// Take the array out of the value, or give the value back
+    pub fn into_array(self) -> Result<JsonArrayContainer, JsonValue>
+    {
+        match self
+        {
+            JsonValue::JsonArray(array) => Ok(JsonArrayContainer(array)),
+            value => Err(value),
+        }
+    }
 }
 
 impl<'a> JsonObjectContainerRef<'a>
@@ -256,11 +320,22 @@ impl<'a> JsonObjectContainerRef<'a>
         }
     }
 
-    pub fn lenght(&self) -> usize 
+    pub fn len(&self) -> usize
     {
         self.0.len()
     }
 
+    pub fn is_empty(&self) -> bool
+    {
+        self.0.is_empty()
+    }
+
+    #[deprecated(note = "renamed to `len`")]
+    pub fn lenght(&self) -> usize
+    {
+        self.len()
+    }
+
     pub fn contains_key(&self, key : &str) -> bool
     {
         self.0.contains_key(key)
@@ -380,16 +455,124 @@ impl<'a> JsonObjectContainerMutRef<'a>
         }
     }
 
-    pub fn lenght(&self) -> usize 
+    pub fn len(&self) -> usize
     {
         self.0.len()
     }
 
+    pub fn is_empty(&self) -> bool
+    {
+        self.0.is_empty()
+    }
+
+    #[deprecated(note = "renamed to `len`")]
+    pub fn lenght(&self) -> usize
+    {
+        self.len()
+    }
+
     pub fn contains_key(&self, key : &str) -> bool
     {
         self.0.contains_key(key)
     }
 
+    /// Insert a member, returning the value it replaces
+    ///
+    /// A new key goes last, a key already present keeps its position
+    pub fn insert(&mut self, key : &str, value : JsonValue) -> Option<JsonValue>
+    {
+        self.0.insert(key.to_owned(), value)
+    }
+
+    /// Remove a member, keeping the order of the others
+    pub fn remove(&mut self, key : &str) -> Option<JsonValue>
+    {
+        self.0.remove(key)
+    }
+
+    /// Keep only the members for which `keep` returns true
+    pub fn retain<F>(&mut self, keep : F)
+        where F : FnMut(&str, &mut JsonValue) -> bool
+    {
+        self.0.retain(keep)
+    }
+
+    /// The member `key`, which may not exist yet
+    pub fn entry(&mut self, key : &str) -> Entry<JsonValue>
+    {
+        self.0.entry(key.to_owned())
+    }
+
+    pub fn clear(&mut self)
+    {
+        self.0.clear()
+    }
+
+    /// Insert `value` as `insert` does and return it
+    fn insert_value(&mut self, key : &str, value : JsonValue) -> &mut JsonValue
+    {
+        match self.0.entry(key.to_owned())
+        {
+            Entry::Occupied(mut entry) =>
+            {
+                entry.insert(value);
+                entry.into_mut()
+            },
+            Entry::Vacant(entry) => entry.insert(value),
+        }
+    }
+
+    /// Insert an empty object and return it
+    pub fn insert_object(&mut self, key : &str) -> JsonObjectContainerMutRef
+    {
+        match *self.insert_value(key, JsonValue::JsonObject(JsonObject::new()))
+        {
+            JsonValue::JsonObject(ref mut object) => JsonObjectContainerMutRef(object),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Insert an empty array and return it
+    pub fn insert_array(&mut self, key : &str) -> JsonArrayContainerMutRef
+    {
+        match *self.insert_value(key, JsonValue::JsonArray(JsonArray::new()))
+        {
+            JsonValue::JsonArray(ref mut array) => JsonArrayContainerMutRef(array),
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn insert_string(&mut self, key : &str, string : &str) -> &mut String
+    {
+        match *self.insert_value(key, JsonValue::JsonString(string.to_owned()))
+        {
+            JsonValue::JsonString(ref mut string) => string,
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn insert_number(&mut self, key : &str, number : f64) -> &mut f64
+    {
+        match *self.insert_value(key, JsonValue::JsonNumber(number))
+        {
+            JsonValue::JsonNumber(ref mut number) => number,
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn insert_bool(&mut self, key : &str, b : bool) -> &mut bool
+    {
+        match *self.insert_value(key, JsonValue::JsonBool(b))
+        {
+            JsonValue::JsonBool(ref mut b) => b,
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn insert_null(&mut self, key : &str)
+    {
+        self.insert_value(key, JsonValue::Null);
+    }
 }
 
 impl<'a> JsonArrayContainerRef<'a> {
@@ -453,10 +636,21 @@ impl<'a> JsonArrayContainerRef<'a> {
         }
     }
 
-    pub fn lenght(&self) -> usize 
+    pub fn len(&self) -> usize
     {
         self.0.len()
     }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.0.is_empty()
+    }
+
+    #[deprecated(note = "renamed to `len`")]
+    pub fn lenght(&self) -> usize
+    {
+        self.len()
+    }
     
 }
 
@@ -571,18 +765,612 @@ impl<'a> JsonArrayContainerMutRef<'a> {
         }
     }
 
-    pub fn lenght(&self) -> usize 
+    pub fn len(&self) -> usize
     {
         self.0.len()
     }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.0.is_empty()
+    }
+
+    #[deprecated(note = "renamed to `len`")]
+    pub fn lenght(&self) -> usize
+    {
+        self.len()
+    }
+    pub fn push(&mut self, value : JsonValue)
+    {
+        self.0.push(value)
+    }
+
+    pub fn pop(&mut self) -> Option<JsonValue>
+    {
+        self.0.pop()
+    }
+
+    /// Insert `value` at `index`, moving the elements after it
+    ///
+    /// Gives `value` back if `index` is past the end
+    pub fn insert(&mut self, index : usize, value : JsonValue) -> Result<(), JsonValue>
+    {
+        if index <= self.0.len()
+        {
+            self.0.insert(index, value);
+            Ok(())
+        }
+        else
+        {
+            Err(value)
+        }
+    }
+
+    /// Remove the element at `index`, moving the elements after it
+    pub fn remove(&mut self, index : usize) -> Option<JsonValue>
+    {
+        if index < self.0.len()
+        {
+            Some(self.0.remove(index))
+        }
+        else
+        {
+            None
+        }
+    }
+
+    /// Keep the first `len` elements
+    pub fn truncate(&mut self, len : usize)
+    {
+        self.0.truncate(len)
+    }
+
+    /// Keep only the elements for which `keep` returns true
+    pub fn retain<F>(&mut self, keep : F)
+        where F : FnMut(&JsonValue) -> bool
+    {
+        self.0.retain(keep)
+    }
+
+    /// Sort the elements, keeping the order of equal ones
+    pub fn sort_by<F>(&mut self, compare : F)
+        where F : FnMut(&JsonValue, &JsonValue) -> Ordering
+    {
+        self.0.sort_by(compare)
+    }
+
+    pub fn clear(&mut self)
+    {
+        self.0.clear()
+    }
+
+    /// Push `value` and return it
+    fn push_value(&mut self, value : JsonValue) -> &mut JsonValue
+    {
+        self.0.push(value);
+        let last = self.0.len() - 1;
+        &mut self.0[last]
+    }
+
+    /// Push an empty object and return it
+    pub fn push_object(&mut self) -> JsonObjectContainerMutRef
+    {
+        match *self.push_value(JsonValue::JsonObject(JsonObject::new()))
+        {
+            JsonValue::JsonObject(ref mut object) => JsonObjectContainerMutRef(object),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Push an empty array and return it
+    pub fn push_array(&mut self) -> JsonArrayContainerMutRef
+    {
+        match *self.push_value(JsonValue::JsonArray(JsonArray::new()))
+        {
+            JsonValue::JsonArray(ref mut array) => JsonArrayContainerMutRef(array),
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn push_string(&mut self, string : &str) -> &mut String
+    {
+        match *self.push_value(JsonValue::JsonString(string.to_owned()))
+        {
+            JsonValue::JsonString(ref mut string) => string,
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn push_number(&mut self, number : f64) -> &mut f64
+    {
+        match *self.push_value(JsonValue::JsonNumber(number))
+        {
+            JsonValue::JsonNumber(ref mut number) => number,
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn push_bool(&mut self, b : bool) -> &mut bool
+    {
+        match *self.push_value(JsonValue::JsonBool(b))
+        {
+            JsonValue::JsonBool(ref mut b) => b,
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn push_null(&mut self)
+    {
+        self.0.push(JsonValue::Null)
+    }
 }
 
 impl JsonObjectContainer
 {
-     // to be implemented
+    pub fn new() -> JsonObjectContainer
+    {
+        JsonObjectContainer(JsonObject::new())
+    }
+
+    pub fn with_capacity(capacity : usize) -> JsonObjectContainer
+    {
+        JsonObjectContainer(JsonObject::with_capacity(capacity))
+    }
+
+    /// Add the member `key`, replacing any earlier one
+    pub fn with(mut self, key : &str, value : JsonValue) -> JsonObjectContainer
+    {
+        self.0.insert(key.to_owned(), value);
+        self
+    }
+
+    pub fn with_object(self, key : &str, object : JsonObjectContainer) -> JsonObjectContainer
+    {
+        self.with(key, object.into())
+    }
+
+    pub fn with_array(self, key : &str, array : JsonArrayContainer) -> JsonObjectContainer
+    {
+        self.with(key, array.into())
+    }
+
+    pub fn with_string(self, key : &str, string : &str) -> JsonObjectContainer
+    {
+        self.with(key, JsonValue::JsonString(string.to_owned()))
+    }
+
+    pub fn with_number(self, key : &str, number : f64) -> JsonObjectContainer
+    {
+        self.with(key, JsonValue::JsonNumber(number))
+    }
+
+    pub fn with_bool(self, key : &str, b : bool) -> JsonObjectContainer
+    {
+        self.with(key, JsonValue::JsonBool(b))
+    }
+
+    pub fn with_null(self, key : &str) -> JsonObjectContainer
+    {
+        self.with(key, JsonValue::Null)
+    }
+
+    pub fn as_container_ref(&self) -> JsonObjectContainerRef
+    {
+        JsonObjectContainerRef(&self.0)
+    }
+
+    pub fn as_container_mut(&mut self) -> JsonObjectContainerMutRef
+    {
+        JsonObjectContainerMutRef(&mut self.0)
+    }
+
+    pub fn as_object(&self) -> &JsonObject
+    {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> JsonObject
+    {
+        self.0
+    }
+
+    pub fn get(&self, key : &str) -> Option<&JsonValue>
+    {
+        self.0.get(key)
+    }
+
+    pub fn get_mut(&mut self, key : &str) -> Option<&mut JsonValue>
+    {
+        self.0.get_mut(key)
+    }
+
+    pub fn get_object_ref<'a>(&'a self, key : &str) -> Result<Option<JsonObjectContainerRef<'a>>, InvalidValueError<'a>>
+    {
+        match self.0.get(key)
+        {
+            Some(&JsonValue::JsonObject(ref object)) => Ok(Some(JsonObjectContainerRef(object))),
+            Some(value) => Err(InvalidValueError{value}),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_object_mut<'a>(&'a mut self, key : &str) -> Result<Option<JsonObjectContainerMutRef<'a>>, InvalidValueError<'a>>
+    {
+        match self.0.get_mut(key)
+        {
+            Some(JsonValue::JsonObject(ref mut object)) => Ok(Some(JsonObjectContainerMutRef(object))),
+            Some(value) => Err(InvalidValueError{value}),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_array_ref<'a>(&'a self, key : &str) -> Result<Option<JsonArrayContainerRef<'a>>, InvalidValueError<'a>>
+    {
+        match self.0.get(key)
+        {
+            Some(&JsonValue::JsonArray(ref array)) => Ok(Some(JsonArrayContainerRef(array))),
+            Some(value) => Err(InvalidValueError{value}),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_array_mut<'a>(&'a mut self, key : &str) -> Result<Option<JsonArrayContainerMutRef<'a>>, InvalidValueError<'a>>
+    {
+        match self.0.get_mut(key)
+        {
+            Some(JsonValue::JsonArray(ref mut array)) => Ok(Some(JsonArrayContainerMutRef(array))),
+            Some(value) => Err(InvalidValueError{value}),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_number<'a>(&'a self, key : &str) -> Result<Option<f64>, InvalidValueError<'a>>
+    {
+        match self.0.get(key)
+        {
+            Some(&JsonValue::JsonNumber(number)) => Ok(Some(number)),
+            Some(value) => Err(InvalidValueError{value}),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_number_mut<'a>(&'a mut self, key : &str) -> Result<Option<&'a mut f64>, InvalidValueError<'a>>
+    {
+        match self.0.get_mut(key)
+        {
+            Some(JsonValue::JsonNumber(ref mut number)) => Ok(Some(number)),
+            Some(value) => Err(InvalidValueError{value}),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_bool<'a>(&'a self, key : &str) -> Result<Option<bool>, InvalidValueError<'a>>
+    {
+        match self.0.get(key)
+        {
+            Some(&JsonValue::JsonBool(b)) => Ok(Some(b)),
+            Some(value) => Err(InvalidValueError{value}),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_bool_mut<'a>(&'a mut self, key : &str) -> Result<Option<&'a mut bool>, InvalidValueError<'a>>
+    {
+        match self.0.get_mut(key)
+        {
+            Some(JsonValue::JsonBool(ref mut b)) => Ok(Some(b)),
+            Some(value) => Err(InvalidValueError{value}),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_string_ref<'a>(&'a self, key : &str) -> Result<Option<&'a str>, InvalidValueError<'a>>
+    {
+        match self.0.get(key)
+        {
+            Some(&JsonValue::JsonString(ref string)) => Ok(Some(string)),
+            Some(value) => Err(InvalidValueError{value}),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_string_mut<'a>(&'a mut self, key : &str) -> Result<Option<&'a mut String>, InvalidValueError<'a>>
+    {
+        match self.0.get_mut(key)
+        {
+            Some(JsonValue::JsonString(ref mut string)) => Ok(Some(string)),
+            Some(value) => Err(InvalidValueError{value}),
+            None => Ok(None),
+        }
+    }
+
+    /// Whether the member is null or missing
+    pub fn is_null(&self, key : &str) -> bool
+    {
+        match self.0.get(key)
+        {
+            Some(&JsonValue::Null) | None => true,
+            Some(_) => false,
+        }
+    }
+
+    pub fn contains_key(&self, key : &str) -> bool
+    {
+        self.0.contains_key(key)
+    }
+
+    pub fn len(&self) -> usize
+    {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.0.is_empty()
+    }
+
+    /// Members in order
+    pub fn iter(&self) -> ::map::Iter<JsonValue>
+    {
+        self.0.iter()
+    }
 }
 
 impl JsonArrayContainer
 {
-    // to be implemented
+    pub fn new() -> JsonArrayContainer
+    {
+        JsonArrayContainer(JsonArray::new())
+    }
+
+    pub fn with_capacity(capacity : usize) -> JsonArrayContainer
+    {
+        JsonArrayContainer(JsonArray::with_capacity(capacity))
+    }
+
+    /// Append an element
+    pub fn with(mut self, value : JsonValue) -> JsonArrayContainer
+    {
+        self.0.push(value);
+        self
+    }
+
+    pub fn with_object(self, object : JsonObjectContainer) -> JsonArrayContainer
+    {
+        self.with(object.into())
+    }
+
+    pub fn with_array(self, array : JsonArrayContainer) -> JsonArrayContainer
+    {
+        self.with(array.into())
+    }
+
+    pub fn with_string(self, string : &str) -> JsonArrayContainer
+    {
+        self.with(JsonValue::JsonString(string.to_owned()))
+    }
+
+    pub fn with_number(self, number : f64) -> JsonArrayContainer
+    {
+        self.with(JsonValue::JsonNumber(number))
+    }
+
+    pub fn with_bool(self, b : bool) -> JsonArrayContainer
+    {
+        self.with(JsonValue::JsonBool(b))
+    }
+
+    pub fn with_null(self) -> JsonArrayContainer
+    {
+        self.with(JsonValue::Null)
+    }
+
+    pub fn as_container_ref(&self) -> JsonArrayContainerRef
+    {
+        JsonArrayContainerRef(&self.0)
+    }
+
+    pub fn as_container_mut(&mut self) -> JsonArrayContainerMutRef
+    {
+        JsonArrayContainerMutRef(&mut self.0)
+    }
+
+    pub fn as_slice(&self) -> &[JsonValue]
+    {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> JsonArray
+    {
+        self.0
+    }
+
+    pub fn get(&self, index : usize) -> Option<&JsonValue>
+    {
+        self.0.get(index)
+    }
+
+    pub fn get_mut(&mut self, index : usize) -> Option<&mut JsonValue>
+    {
+        self.0.get_mut(index)
+    }
+
+    pub fn get_object_ref<'a>(&'a self, index : usize) -> Result<Option<JsonObjectContainerRef<'a>>, InvalidValueError<'a>>
+    {
+        match self.0.get(index)
+        {
+            Some(JsonValue::JsonObject(ref object)) => Ok(Some(JsonObjectContainerRef(object))),
+            Some(value) => Err(InvalidValueError{value}),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_object_mut<'a>(&'a mut self, index : usize) -> Result<Option<JsonObjectContainerMutRef<'a>>, InvalidValueError<'a>>
+    {
+        match self.0.get_mut(index)
+        {
+            Some(JsonValue::JsonObject(ref mut object)) => Ok(Some(JsonObjectContainerMutRef(object))),
+            Some(value) => Err(InvalidValueError{value}),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_array_ref<'a>(&'a self, index : usize) -> Result<Option<JsonArrayContainerRef<'a>>, InvalidValueError<'a>>
+    {
+        match self.0.get(index)
+        {
+            Some(JsonValue::JsonArray(ref array)) => Ok(Some(JsonArrayContainerRef(array))),
+            Some(value) => Err(InvalidValueError{value}),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_array_mut<'a>(&'a mut self, index : usize) -> Result<Option<JsonArrayContainerMutRef<'a>>, InvalidValueError<'a>>
+    {
+        match self.0.get_mut(index)
+        {
+            Some(JsonValue::JsonArray(ref mut array)) => Ok(Some(JsonArrayContainerMutRef(array))),
+            Some(value) => Err(InvalidValueError{value}),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_string_ref<'a>(&'a self, index : usize) -> Result<Option<&'a str>, InvalidValueError<'a>>
+    {
+        match self.0.get(index)
+        {
+            Some(JsonValue::JsonString(ref string)) => Ok(Some(string)),
+            Some(value) => Err(InvalidValueError{value}),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_string_mut<'a>(&'a mut self, index : usize) -> Result<Option<&'a mut String>, InvalidValueError<'a>>
+    {
+        match self.0.get_mut(index)
+        {
+            Some(JsonValue::JsonString(ref mut string)) => Ok(Some(string)),
+            Some(value) => Err(InvalidValueError{value}),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_number<'a>(&'a self, index : usize) -> Result<Option<f64>, InvalidValueError<'a>>
+    {
+        match self.0.get(index)
+        {
+            Some(JsonValue::JsonNumber(number)) => Ok(Some(*number)),
+            Some(value) => Err(InvalidValueError{value}),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_number_mut<'a>(&'a mut self, index : usize) -> Result<Option<&'a mut f64>, InvalidValueError<'a>>
+    {
+        match self.0.get_mut(index)
+        {
+            Some(JsonValue::JsonNumber(ref mut number)) => Ok(Some(number)),
+            Some(value) => Err(InvalidValueError{value}),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_bool<'a>(&'a self, index : usize) -> Result<Option<bool>, InvalidValueError<'a>>
+    {
+        match self.0.get(index)
+        {
+            Some(JsonValue::JsonBool(b)) => Ok(Some(*b)),
+            Some(value) => Err(InvalidValueError{value}),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_bool_mut<'a>(&'a mut self, index : usize) -> Result<Option<&'a mut bool>, InvalidValueError<'a>>
+    {
+        match self.0.get_mut(index)
+        {
+            Some(JsonValue::JsonBool(ref mut b)) => Ok(Some(b)),
+            Some(value) => Err(InvalidValueError{value}),
+            None => Ok(None),
+        }
+    }
+
+    /// Whether the element is null or missing
+    pub fn is_null(&self, index : usize) -> bool
+    {
+        match self.0.get(index)
+        {
+            Some(JsonValue::Null) | None => true,
+            Some(_) => false,
+        }
+    }
+
+    pub fn len(&self) -> usize
+    {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> ::std::slice::Iter<JsonValue>
+    {
+        self.0.iter()
+    }
+}
+
+impl From<JsonObject> for JsonObjectContainer
+{
+    fn from(object : JsonObject) -> JsonObjectContainer
+    {
+        JsonObjectContainer(object)
+    }
+}
+
+impl From<JsonArray> for JsonArrayContainer
+{
+    fn from(array : JsonArray) -> JsonArrayContainer
+    {
+        JsonArrayContainer(array)
+    }
+}
+
+impl From<JsonObjectContainer> for JsonValue
+{
+    fn from(object : JsonObjectContainer) -> JsonValue
+    {
+        JsonValue::JsonObject(object.0)
+    }
+}
+
+impl From<JsonArrayContainer> for JsonValue
+{
+    fn from(array : JsonArrayContainer) -> JsonValue
+    {
+        JsonValue::JsonArray(array.0)
+    }
+}
+
+/// Fails with the value itself if it is not an object
+impl TryFrom<JsonValue> for JsonObjectContainer
+{
+    type Error = JsonValue;
+
+    fn try_from(value : JsonValue) -> Result<JsonObjectContainer, JsonValue>
+    {
+        value.into_object()
+    }
+}
+
+/// Fails with the value itself if it is not an array
+impl TryFrom<JsonValue> for JsonArrayContainer
+{
+    type Error = JsonValue;
+
+    fn try_from(value : JsonValue) -> Result<JsonArrayContainer, JsonValue>
+    {
+        value.into_array()
+    }
 }
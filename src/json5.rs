@@ -0,0 +1,185 @@
+use json::{JsonObject,JsonValue};
+use lex::Lex;
+use options::ParserOptions;
+use parse_error::Result;
+use parser::JsonParser;
+use ser;
+use std::fmt::{self, Write};
+use std::slice;
+
+/// Indentation of each nesting level in written JSON5
+const INDENT : &str = "  ";
+
+/// Parse a JSON5 document (https://spec.json5.org)
+///
+/// Accepts every extension of `ParserOptions::json5` on top of JSON.
+pub fn parse<'src>(source : &'src str) -> Result<'src, JsonValue>
+{
+    JsonParser::new(source).with_options(ParserOptions::json5()).parse()
+}
+
+/// Write `value` as idiomatic JSON5
+///
+/// Members and elements go on their own lines, each followed by a comma.
+/// Keys are unquoted when they are identifier names, strings use single
+/// quotes unless double quotes need fewer escapes, and non-finite numbers
+/// are written as `NaN` and `Infinity`
+pub fn to_string(value : &JsonValue) -> String
+{
+    let mut out = String::new();
+    let _ = write(value, &mut out);
+    out
+}
+
+/// An object or array being written, and the members or elements left
+enum Open<'a>
+{
+    Object(<&'a JsonObject as IntoIterator>::IntoIter),
+    Array(slice::Iter<'a, JsonValue>),
+}
+
+/// Write `value` as JSON5 to `out`, see `to_string`
+///
+/// Nested values are kept on an explicit stack, so any depth can be written
+pub fn write<W : Write>(value : &JsonValue, out : &mut W) -> fmt::Result
+{
+    // open containers, and whether they have written a member or element yet
+    let mut stack : Vec<(Open, bool)> = Vec::new();
+    let mut next = Some(value);
+    loop
+    {
+        if let Some(value) = next.take()
+        {
+            match *value
+            {
+                JsonValue::JsonObject(ref object) if !object.is_empty() =>
+                {
+                    out.write_char('{')?;
+                    stack.push((Open::Object(object.iter()), false));
+                },
+                JsonValue::JsonArray(ref array) if !array.is_empty() =>
+                {
+                    out.write_char('[')?;
+                    stack.push((Open::Array(array.iter()), false));
+                },
+                JsonValue::JsonObject(_) => out.write_str("{}")?,
+                JsonValue::JsonArray(_) => out.write_str("[]")?,
+                JsonValue::JsonString(ref string) => write_string(string, out)?,
+                JsonValue::JsonNumber(number) => write_number(number, out)?,
+                JsonValue::JsonBool(b) => write!(out, "{}", b)?,
+                JsonValue::Null => out.write_str("null")?,
+                JsonValue::JsonRaw(ref raw) => out.write_str(raw.as_str())?,
+            }
+        }
+
+        let depth = stack.len();
+        let (open, started) = match stack.last_mut()
+        {
+            None => return Ok(()),
+            Some(&mut (ref mut open, ref mut started)) => (open, started),
+        };
+
+        // every member and element is followed by a comma
+        if *started
+        {
+            out.write_char(',')?;
+        }
+        *started = true;
+
+        let close = match *open
+        {
+            Open::Object(ref mut members) => match members.next()
+            {
+                Some((key, value)) =>
+                {
+                    new_line(depth, out)?;
+                    write_key(key, out)?;
+                    out.write_str(": ")?;
+                    next = Some(value);
+                    continue;
+                },
+                None => '}',
+            },
+            Open::Array(ref mut elements) => match elements.next()
+            {
+                Some(value) =>
+                {
+                    new_line(depth, out)?;
+                    next = Some(value);
+                    continue;
+                },
+                None => ']',
+            },
+        };
+        stack.pop();
+        new_line(depth - 1, out)?;
+        out.write_char(close)?;
+    }
+}
+
+fn new_line<W : Write>(depth : usize, out : &mut W) -> fmt::Result
+{
+    out.write_char('\n')?;
+    for _ in 0..depth
+    {
+        out.write_str(INDENT)?;
+    }
+    Ok(())
+}
+
+/// Writes a key unquoted if it is an identifier name
+fn write_key<W : Write>(key : &str, out : &mut W) -> fmt::Result
+{
+    if !key.is_empty() && !key.contains('\\') && Lex::identifier_length(key.as_bytes()) == key.len()
+    {
+        out.write_str(key)
+    }
+    else
+    {
+        write_string(key, out)
+    }
+}
+
+fn write_string<W : Write>(string : &str, out : &mut W) -> fmt::Result
+{
+    let singles = string.matches('\'').count();
+    let doubles = string.matches('"').count();
+    let quote = if doubles < singles {'"'} else {'\''};
+
+    out.write_char(quote)?;
+    for c in string.chars()
+    {
+        match c
+        {
+            '\\' => out.write_str("\\\\")?,
+            '\x08' => out.write_str("\\b")?,
+            '\x0c' => out.write_str("\\f")?,
+            '\n' => out.write_str("\\n")?,
+            '\r' => out.write_str("\\r")?,
+            '\t' => out.write_str("\\t")?,
+            '\x0b' => out.write_str("\\v")?,
+            '\u{2028}' => out.write_str("\\u2028")?,
+            '\u{2029}' => out.write_str("\\u2029")?,
+            c if c == quote => {out.write_char('\\')?; out.write_char(c)?;},
+            c if (c as u32) < 0x20 => write!(out, "\\x{:02X}", c as u32)?,
+            c => out.write_char(c)?,
+        }
+    }
+    out.write_char(quote)
+}
+
+fn write_number<W : Write>(number : f64, out : &mut W) -> fmt::Result
+{
+    if number.is_nan()
+    {
+        out.write_str("NaN")
+    }
+    else if number.is_infinite()
+    {
+        out.write_str(if number > 0.0 {"Infinity"} else {"-Infinity"})
+    }
+    else
+    {
+        ser::write_shortest(number, out)
+    }
+}
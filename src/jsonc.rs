@@ -0,0 +1,411 @@
+use json::{JsonObject,JsonValue};
+use lex::{Lex,TokenType};
+use options::ParserOptions;
+use parse_error::Result;
+use parser::JsonParser;
+use pointer::JsonPointer;
+use ser::{self,Escaping,PrettyConfig,SerializeError,ToJson};
+use source_map::{Location,Position};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::{iter, mem, result, slice};
+
+/// The two forms of comment in JSONC
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum CommentKind
+{
+    /// `// ...` up to the end of the line
+    Line,
+    /// `/* ... */`, possibly spanning lines
+    Block,
+}
+
+/// Where a comment sits relative to the value it is attached to
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Placement
+{
+    /// Before the value, typically on the lines above it
+    Before,
+    /// After the value, on the line where it ends or at the end of its container
+    After,
+    /// Inside the value, an empty object or array
+    Inside,
+}
+
+/// A comment in a JSONC document and the value it belongs to
+#[derive(Debug,Clone,PartialEq)]
+pub struct Comment
+{
+    /// The comment as written, including its delimiters
+    pub text : String,
+    pub kind : CommentKind,
+    /// Byte offset of the comment in the source
+    pub offset : usize,
+    /// Line of the comment, counting from 1
+    pub line : usize,
+    /// Column of the comment in characters, counting from 1
+    pub column : usize,
+    /// The value the comment is attached to
+    pub path : JsonPointer,
+    pub placement : Placement,
+}
+
+impl Comment
+{
+    /// The text between the delimiters
+    pub fn body(&self) -> &str
+    {
+        match self.kind
+        {
+            CommentKind::Line => &self.text[2..],
+            CommentKind::Block => &self.text[2..self.text.len() - 2],
+        }
+    }
+}
+
+/// A parsed JSONC document with its comments in source order
+#[derive(Debug)]
+pub struct JsoncDocument
+{
+    pub value : JsonValue,
+    pub comments : Vec<Comment>,
+}
+
+/// Parse a JSONC document, such as VS Code's `settings.json`
+///
+/// Accepts the syntax of `ParserOptions::jsonc` and keeps every comment.
+/// A comment on the line where a value ends is attached after it, any
+/// other comment before the value that follows it. Comments with no
+/// value following them in their container go after its last value,
+/// or inside it when it is empty.
+pub fn parse<'src>(source : &'src str) -> Result<'src, JsoncDocument>
+{
+    let value = JsonParser::new(source).with_options(ParserOptions::jsonc()).parse()?;
+    let comments = attach_comments(source);
+    Ok(JsoncDocument{value, comments})
+}
+
+/// Write `document` as JSONC, with its comments around their values
+///
+/// The value is written as pretty JSON with a comma after every member
+/// and element. Comments before a value go on their own lines above it,
+/// comments after it at the end of its line and comments inside an empty
+/// object or array between its brackets, so parsing the output attaches
+/// each comment to the same value again. Fails on NaN and infinities.
+pub fn to_string(document : &JsoncDocument) -> result::Result<String, SerializeError>
+{
+    let mut out = String::new();
+    write(document, &mut out)?;
+    Ok(out)
+}
+
+/// An object or array being written, and the members or elements left
+enum Open<'a>
+{
+    Object(<&'a JsonObject as IntoIterator>::IntoIter),
+    Array(iter::Enumerate<slice::Iter<'a, JsonValue>>),
+}
+
+/// Write `document` as JSONC to `out`, see `to_string`
+///
+/// Nested values are kept on an explicit stack, so any depth can be written
+pub fn write<W : Write>(document : &JsoncDocument, out : &mut W) -> result::Result<(), SerializeError>
+{
+    let pretty = PrettyConfig::new();
+    let mut attached : HashMap<&JsonPointer, Vec<&Comment>> = HashMap::new();
+    for comment in &document.comments
+    {
+        attached.entry(&comment.path).or_default().push(comment);
+    }
+
+    for comment in comments_at(&attached, &JsonPointer::root(), Placement::Before)
+    {
+        out.write_str(&comment.text)?;
+        ser::new_line(&pretty, 0, out)?;
+    }
+
+    // open containers with their paths
+    let mut stack : Vec<(Open, JsonPointer)> = Vec::new();
+    let mut next = Some((&document.value, JsonPointer::root()));
+    while let Some((value, path)) = next.take()
+    {
+        let depth = stack.len();
+        let mut finished = match *value
+        {
+            JsonValue::JsonObject(ref object) if !object.is_empty() =>
+            {
+                out.write_char('{')?;
+                stack.push((Open::Object(object.iter()), path));
+                None
+            },
+            JsonValue::JsonArray(ref array) if !array.is_empty() =>
+            {
+                out.write_char('[')?;
+                stack.push((Open::Array(array.iter().enumerate()), path));
+                None
+            },
+            JsonValue::JsonObject(_) | JsonValue::JsonArray(_) =>
+            {
+                let (open, close) = if value.is_object() {('{', '}')} else {('[', ']')};
+                out.write_char(open)?;
+                let inside = comments_at(&attached, &path, Placement::Inside);
+                for comment in &inside
+                {
+                    ser::new_line(&pretty, depth + 1, out)?;
+                    out.write_str(&comment.text)?;
+                }
+                if !inside.is_empty()
+                {
+                    ser::new_line(&pretty, depth, out)?;
+                }
+                out.write_char(close)?;
+                Some(path)
+            },
+            _ =>
+            {
+                value.write_json(out)?;
+                Some(path)
+            },
+        };
+
+        // a complete value is followed by its comma and the comments after it,
+        // then comes the next member or element, or the end of its container
+        loop
+        {
+            let depth = stack.len();
+            if let Some(path) = finished.take()
+            {
+                if depth > 0
+                {
+                    out.write_char(',')?;
+                }
+                let mut after_line_comment = false;
+                for comment in comments_at(&attached, &path, Placement::After)
+                {
+                    if after_line_comment
+                    {
+                        ser::new_line(&pretty, depth, out)?;
+                    }
+                    else
+                    {
+                        out.write_char(' ')?;
+                    }
+                    out.write_str(&comment.text)?;
+                    after_line_comment = comment.kind == CommentKind::Line;
+                }
+            }
+
+            let child = match stack.last_mut()
+            {
+                None =>
+                {
+                    ser::new_line(&pretty, 0, out)?;
+                    return Ok(());
+                },
+                Some(&mut (Open::Object(ref mut members), ref path)) => members.next().map(|(key, value)|
+                {
+                    let mut member = path.clone();
+                    member.push(key);
+                    (Some(key), value, member)
+                }),
+                Some(&mut (Open::Array(ref mut elements), ref path)) => elements.next().map(|(index, value)|
+                {
+                    let mut element = path.clone();
+                    element.push(&index.to_string());
+                    (None, value, element)
+                }),
+            };
+            match child
+            {
+                Some((key, value, path)) =>
+                {
+                    for comment in comments_at(&attached, &path, Placement::Before)
+                    {
+                        ser::new_line(&pretty, depth, out)?;
+                        out.write_str(&comment.text)?;
+                    }
+                    ser::new_line(&pretty, depth, out)?;
+                    if let Some(key) = key
+                    {
+                        ser::write_string(key, Escaping::minimal(), out)?;
+                        out.write_str(": ")?;
+                    }
+                    next = Some((value, path));
+                    break;
+                },
+                None =>
+                {
+                    let (close, path) = match stack.pop()
+                    {
+                        Some((Open::Object(_), path)) => ('}', path),
+                        Some((Open::Array(_), path)) => (']', path),
+                        None => unreachable!(),
+                    };
+                    ser::new_line(&pretty, depth - 1, out)?;
+                    out.write_char(close)?;
+                    finished = Some(path);
+                },
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The comments attached to the value at `path` with `placement`, in source order
+fn comments_at<'a>(attached : &HashMap<&JsonPointer, Vec<&'a Comment>>, path : &JsonPointer,
+                   placement : Placement) -> Vec<&'a Comment>
+{
+    match attached.get(path)
+    {
+        Some(comments) => comments.iter().filter(|comment| comment.placement == placement).cloned().collect(),
+        None => Vec::new(),
+    }
+}
+
+/// An object or array enclosing the comments being read
+struct Container
+{
+    path : JsonPointer,
+    is_object : bool,
+    key : String,
+    elements : usize,
+    last_child : Option<JsonPointer>,
+}
+
+/// Attaches the comments of a well-formed document to its values
+fn attach_comments(source : &str) -> Vec<Comment>
+{
+    let mut lexer = Lex::new(source).with_options(ParserOptions::jsonc()).keep_comments();
+    let mut position = Position::new(source);
+    let mut comments : Vec<Comment> = Vec::new();
+    // comments waiting for the next value to start
+    let mut pending : Vec<usize> = Vec::new();
+    let mut stack : Vec<Container> = Vec::new();
+    // the last value completed in the innermost container, and the offset where it ends
+    let mut previous : Option<(JsonPointer, usize)> = None;
+    let mut expect_key = false;
+
+    loop
+    {
+        let token = lexer.next();
+        let offset = token.span.as_ptr() as usize - source.as_ptr() as usize;
+        let end = offset + token.span.len();
+
+        match token.token_type
+        {
+            TokenType::Comment =>
+            {
+                let Location{line, column, ..} = position.at(offset);
+                let kind = if token.span.starts_with("//") {CommentKind::Line} else {CommentKind::Block};
+                let (path, placement) = match previous
+                {
+                    Some((ref path, value_end)) if !source[value_end..offset].contains('\n') =>
+                    {
+                        (path.clone(), Placement::After)
+                    },
+                    _ =>
+                    {
+                        pending.push(comments.len());
+                        (JsonPointer::root(), Placement::Before)
+                    },
+                };
+                comments.push(Comment{text : token.span.to_owned(), kind, offset, line, column, path, placement});
+            },
+            TokenType::String(ref key) if expect_key =>
+            {
+                if let Some(top) = stack.last_mut()
+                {
+                    top.key = key.clone();
+                }
+                expect_key = false;
+            },
+            TokenType::Colon => {},
+            TokenType::Comma =>
+            {
+                expect_key = stack.last().map_or(false, |top| top.is_object);
+            },
+            TokenType::RightBrace | TokenType::RightBracket =>
+            {
+                let container = match stack.pop()
+                {
+                    Some(container) => container,
+                    None => break,
+                };
+                match container.last_child
+                {
+                    Some(ref child) => resolve(&mut comments, &mut pending, child, Placement::After),
+                    None => resolve(&mut comments, &mut pending, &container.path, Placement::Inside),
+                }
+                if let Some(top) = stack.last_mut()
+                {
+                    top.last_child = Some(container.path.clone());
+                }
+                previous = Some((container.path, end));
+                expect_key = false;
+            },
+            TokenType::End | TokenType::Error | TokenType::LongString =>
+            {
+                match previous
+                {
+                    Some((ref path, _)) => resolve(&mut comments, &mut pending, path, Placement::After),
+                    None => resolve(&mut comments, &mut pending, &JsonPointer::root(), Placement::Before),
+                }
+                break;
+            },
+
+            // the start of a value
+            token_type =>
+            {
+                let path = match stack.last_mut()
+                {
+                    Some(top) =>
+                    {
+                        let mut path = top.path.clone();
+                        if top.is_object
+                        {
+                            path.push(&top.key);
+                        }
+                        else
+                        {
+                            path.push(&top.elements.to_string());
+                            top.elements += 1;
+                        }
+                        path
+                    },
+                    None => JsonPointer::root(),
+                };
+                resolve(&mut comments, &mut pending, &path, Placement::Before);
+
+                match token_type
+                {
+                    TokenType::LeftBrace | TokenType::LeftBracket =>
+                    {
+                        let is_object = token_type == TokenType::LeftBrace;
+                        stack.push(Container{path, is_object, key : String::new(), elements : 0, last_child : None});
+                        previous = None;
+                        expect_key = is_object;
+                    },
+                    _ =>
+                    {
+                        if let Some(top) = stack.last_mut()
+                        {
+                            top.last_child = Some(path.clone());
+                        }
+                        previous = Some((path, end));
+                    },
+                }
+            },
+        }
+    }
+    comments
+}
+
+/// Attaches the pending comments to the value at `path`
+fn resolve(comments : &mut [Comment], pending : &mut Vec<usize>, path : &JsonPointer, placement : Placement)
+{
+    for index in mem::replace(pending, Vec::new())
+    {
+        comments[index].path = path.clone();
+        comments[index].placement = placement;
+    }
+}
@@ -1,22 +1,30 @@
-use std::{char, str};
+use options::ParserOptions;
+use std::borrow::Cow;
+use std::{char, f64, str, usize};
 
 /// A JSON lexical analyzer of UTF-8 string
 /// 
 /// Produces JSON tokens according to RFC 7159
 /// Returns an error token if it encounters invalid tokens
 /// with invalid bytes in a span
+///
+/// The syntax extensions enabled in its `ParserOptions`
+/// are accepted as well
 pub(crate) struct Lex<'src> {
     source: &'src [u8],
+    options: ParserOptions,
+    keep_comments: bool,
+    max_string_length: Option<usize>,
 }
 
-#[derive(Debug,PartialEq)]
+#[derive(Debug,PartialEq,Clone)]
 pub(crate) struct Token<'src> 
 {
     pub(crate) span : &'src str,
     pub(crate) token_type : TokenType
 }
 
-#[derive(Debug,PartialEq)]
+#[derive(Debug,PartialEq,Clone)]
 pub(crate) enum TokenType
 {
     // structural characters
@@ -30,10 +38,16 @@ pub(crate) enum TokenType
     //value types
     String(String),
     Number(f64),
+    // an unquoted key, read only when identifiers are enabled
+    Identifier(String),
     Bool(bool),
     Null,
 
     //other types
+    // a comment, returned only by a lexer that keeps comments
+    Comment,
+    // a string longer than the lexer's limit, read only up to the limit
+    LongString,
     Error,
     End,
 }
@@ -42,21 +56,134 @@ impl<'src> Lex<'src> {
     ///create a new lexer from JSON string
     pub(crate) fn new(source : &'src str) -> Lex
     {
-        Lex{source : source.as_bytes()}
+        Lex{source : source.as_bytes(), options : ParserOptions::default(), keep_comments : false, max_string_length : None}
+    }
+
+    /// Accept the syntax extensions enabled in `options`
+    pub(crate) fn with_options(mut self, options : ParserOptions) -> Lex<'src>
+    {
+        self.options = options;
+        self
+    }
+
+    /// Return comments as tokens instead of skipping them like whitespace
+    pub(crate) fn keep_comments(mut self) -> Lex<'src>
+    {
+        self.keep_comments = true;
+        self
+    }
+
+    /// Stop reading a string as soon as it is longer than `limit` bytes,
+    /// returning a `LongString` token instead of allocating all of it
+    pub(crate) fn with_max_string_length(mut self, limit : Option<usize>) -> Lex<'src>
+    {
+        self.max_string_length = limit;
+        self
+    }
+
+    pub(crate) fn options(&self) -> ParserOptions
+    {
+        self.options
+    }
+
+    /// Number of bytes not yet read
+    pub(crate) fn remaining(&self) -> usize
+    {
+        self.source.len()
     }
 
     ///Get next token from lexer
     pub(crate) fn next(&mut self) -> Token<'src>
     {
-        // skip any number of whitespace characters
+        self.read_token(false)
+    }
+
+    ///Get next token from lexer without decoding strings
+    ///
+    /// Strings are checked for termination only and are
+    /// returned empty, so skipping a token never allocates
+    pub(crate) fn skip(&mut self) -> Token<'src>
+    {
+        self.read_token(true)
+    }
+
+    /// Reads the first token of `span` with the same options
+    pub(crate) fn token_at(&self, span : &'src str) -> Token<'src>
+    {
+        Lex::new(span).with_options(self.options).next()
+    }
+
+    /// Decodes the span of a string or identifier token,
+    /// borrowing it when there are no escape sequences
+    ///
+    /// Returns None if the span is not a well-formed string or identifier
+    pub(crate) fn unescape(&self, span : &'src str) -> Option<Cow<'src, str>>
+    {
+        if span.contains('\\')
+        {
+            match self.token_at(span)
+            {
+                Token{token_type: TokenType::String(string), ..} => Some(Cow::Owned(string)),
+                Token{token_type: TokenType::Identifier(name), ..} => Some(Cow::Owned(name)),
+                _ => None,
+            }
+        }
+        else if span.starts_with('"') || span.starts_with('\'')
+        {
+            Some(Cow::Borrowed(&span[1..span.len() - 1]))
+        }
+        else
+        {
+            Some(Cow::Borrowed(span))
+        }
+    }
+
+    /// Decodes a string or identifier token that was read by skip,
+    /// turning it into an error token if it does not decode
+    pub(crate) fn decode(&self, token : Token<'src>) -> Token<'src>
+    {
+        let decoded = match token.token_type
+        {
+            TokenType::String(_) => self.unescape(token.span).map(|string| TokenType::String(string.into_owned())),
+            TokenType::Identifier(_) => self.unescape(token.span).map(|name| TokenType::Identifier(name.into_owned())),
+            _ => return token,
+        };
+        Token{span : token.span, token_type : decoded.unwrap_or(TokenType::Error)}
+    }
+
+    fn read_token(&mut self, skip_strings : bool) -> Token<'src>
+    {
+        // skip any number of whitespace characters and comments
         loop {
             match *self.source {
                 [b, ref rest..] if [b' ', b'\t', b'\r', b'\n'].contains(&b) => self.source = rest,
-                _ => break,
+                [b'/', b'/', ref rest..] if self.options.comments && !self.keep_comments =>
+                {
+                    self.source = Self::skip_line_comment(rest);
+                },
+                [b'/', b'*', ref rest..] if self.options.comments && !self.keep_comments =>
+                {
+                    // an unterminated comment is left to be read as an error token
+                    match Self::skip_block_comment(rest)
+                    {
+                        Some(rest) => self.source = rest,
+                        None => break,
+                    }
+                },
+                _ =>
+                {
+                    let space = if self.options.extended_whitespace {Self::extended_space(self.source)} else {0};
+                    if space == 0
+                    {
+                        break;
+                    }
+                    self.source = &self.source[space..];
+                },
             }
         }
 
         // Determine the type of the token by its first byte
+        let limit = self.max_string_length.unwrap_or(usize::MAX);
         let (token_type, rest) = match *self.source
         {
             //read structural characters
@@ -67,47 +194,39 @@ impl<'src> Lex<'src> {
             [b',', ref rest..] => (TokenType::Comma, rest),
             [b':', ref rest..] => (TokenType::Colon, rest),
 
-            //read a string
-            [b'"', ref rest..] => Self::read_string(rest),
-            
-            //read a number
-            ref rest @ [b'-', ..] | ref rest @ [b'0'..=b'9', ..] => Self::read_number(rest),
-            [b't',b'r',b'u',b'e', ref rest..] => 
+            //read a comment
+            [b'/', b'/', ref rest..] if self.options.comments => (TokenType::Comment, Self::skip_line_comment(rest)),
+            [b'/', b'*', ref rest..] if self.options.comments =>
             {
-                match *rest
+                match Self::skip_block_comment(rest)
                 {
-                    [] => (TokenType::Bool(true),rest),
-                    [b,..] if [b' ', b'\t', b'\r', b'\n',b'{',b'}',b'[',b']',b',',b':'].contains(&b) =>
-                    {
-                        (TokenType::Bool(true),rest)
-                    },
-                    _ => (TokenType::Error,rest),
+                    Some(rest) => (TokenType::Comment, rest),
+                    None => (TokenType::Error, &rest[rest.len()..]),
                 }
             },
-            [b'f',b'a',b'l',b's',b'e', ref rest..] => 
-            {
-                match *rest
-                {
-                    [] => (TokenType::Bool(false),rest),
-                    [b,..] if [b' ', b'\t', b'\r', b'\n',b'{',b'}',b'[',b']',b',',b':'].contains(&b) =>
-                    {
-                        (TokenType::Bool(false),rest)
-                    },
-                    _ => (TokenType::Error,rest),
-                }
-            },
-            [b'n',b'u',b'l',b'l', ref rest..] => 
+
+            //read a string
+            [b'"', ref rest..] if skip_strings => Self::scan_string(rest, b'"', self.options, limit),
+            [b'"', ref rest..] => Self::read_string(rest, b'"', self.options, limit),
+            [b'\'', ref rest..] if self.options.single_quotes && skip_strings => Self::scan_string(rest, b'\'', self.options, limit),
+            [b'\'', ref rest..] if self.options.single_quotes => Self::read_string(rest, b'\'', self.options, limit),
+            
+            //read a number
+            ref rest @ [b'-', ..] | ref rest @ [b'0'..=b'9', ..] => Self::read_number(rest, self.options),
+            ref rest @ [b'+', ..] if self.options.leading_plus => Self::read_number(rest, self.options),
+            ref rest @ [b'.', ..] if self.options.leading_decimal_point => Self::read_number(rest, self.options),
+
+            //read a literal or an identifier, unless the source is in the middle of a character
+            ref rest @ [b, ..] if self.options.identifiers() && !(0x80..0xC0).contains(&b) =>
             {
-                match *rest
-                {
-                    [] => (TokenType::Null,rest),
-                    [b,..] if [b' ', b'\t', b'\r', b'\n',b'{',b'}',b'[',b']',b',',b':'].contains(&b) =>
-                    {
-                        (TokenType::Null,rest)
-                    },
-                    _ => (TokenType::Error,rest),
-                }
+                Self::read_identifier(rest, skip_strings, self.options)
             },
+            [b't',b'r',b'u',b'e', ref rest..] if Self::ends_literal(rest, self.options) => (TokenType::Bool(true),rest),
+            [b't',b'r',b'u',b'e', ref rest..] => (TokenType::Error,rest),
+            [b'f',b'a',b'l',b's',b'e', ref rest..] if Self::ends_literal(rest, self.options) => (TokenType::Bool(false),rest),
+            [b'f',b'a',b'l',b's',b'e', ref rest..] => (TokenType::Error,rest),
+            [b'n',b'u',b'l',b'l', ref rest..] if Self::ends_literal(rest, self.options) => (TokenType::Null,rest),
+            [b'n',b'u',b'l',b'l', ref rest..] => (TokenType::Error,rest),
             [_,ref rest..] => (TokenType::Error,rest),
             [ref rest..] => (TokenType::End,rest),
 
@@ -126,30 +245,97 @@ impl<'src> Lex<'src> {
     
 
 
-    fn read_string(mut source : &'src [u8]) -> (TokenType,&'src [u8])
+    /// True if a literal such as `true` may be followed by `rest`: the end of the
+    /// source, whitespace, a structural character or, if enabled, a comment
+    fn ends_literal(rest : &[u8], options : ParserOptions) -> bool
+    {
+        match *rest
+        {
+            [] => true,
+            [b, ..] if [b' ', b'\t', b'\r', b'\n', b'{', b'}', b'[', b']', b',', b':'].contains(&b) => true,
+            [b'/', ..] if options.comments => true,
+            _ => options.extended_whitespace && Self::extended_space(rest) > 0,
+        }
+    }
+
+    /// Length in bytes of the whitespace character at the start of `source`
+    /// that ECMAScript allows besides the JSON ones, or 0 if there is none
+    fn extended_space(source : &[u8]) -> usize
+    {
+        match *source
+        {
+            // vertical tab, form feed
+            [0x0B, ..] | [0x0C, ..] => 1,
+            // no-break space
+            [0xC2, 0xA0, ..] => 2,
+            // byte order mark, ogham space mark, ideographic space
+            [0xEF, 0xBB, 0xBF, ..] | [0xE1, 0x9A, 0x80, ..] | [0xE3, 0x80, 0x80, ..] => 3,
+            // en quad to hair space, line and paragraph separators, narrow no-break space
+            [0xE2, 0x80, 0x80..=0x8A, ..] | [0xE2, 0x80, 0xA8, ..] | [0xE2, 0x80, 0xA9, ..] | [0xE2, 0x80, 0xAF, ..] => 3,
+            // medium mathematical space
+            [0xE2, 0x81, 0x9F, ..] => 3,
+            _ => 0,
+        }
+    }
+
+    /// Skips a line comment, after '//', up to the end of the line
+    fn skip_line_comment(mut source : &'src [u8]) -> &'src [u8]
+    {
+        while let [b, ref rest..] = *source
+        {
+            if b == b'\n' || b == b'\r'
+            {
+                break;
+            }
+            source = rest;
+        }
+        source
+    }
+
+    /// Skips a block comment, after '/*'
+    /// Returns None if the comment is not terminated
+    fn skip_block_comment(mut source : &'src [u8]) -> Option<&'src [u8]>
+    {
+        loop
+        {
+            match *source
+            {
+                [b'*', b'/', ref rest..] => return Some(rest),
+                [_, ref rest..] => source = rest,
+                [] => return None,
+            }
+        }
+    }
+
+    /// Reads a string closed by `quote`, after the opening quote
+    fn read_string(mut source : &'src [u8], quote : u8, options : ParserOptions, limit : usize) -> (TokenType,&'src [u8])
     {
         let mut buffer = String::new();
         loop
         {
+            if buffer.len() > limit
+            {
+                return (TokenType::LongString,source);
+            }
             match *source
             {
                 //closing quote
-                [b'"', ref rest..] => {source = rest; break;},
-
-                //escape sequences
-                [b'\\', b'"', ref rest..] => {source = rest; buffer.push_str("\"");},
-                [b'\\', b'\\', ref rest..] => {source = rest; buffer.push_str("\\");},
-                [b'\\', b'/', ref rest..] => {source = rest; buffer.push_str("/");},
-                [b'\\', b'b', ref rest..] => {source = rest; buffer.push_str("\x08");},
-                [b'\\', b'f', ref rest..] => {source = rest; buffer.push_str("\x0c");},
-                [b'\\', b'n', ref rest..] => {source = rest; buffer.push_str("\n");},
-                [b'\\', b'r', ref rest..] => {source = rest; buffer.push_str("\r");},
-                [b'\\', b't', ref rest..] => {source = rest; buffer.push_str("\t");},
-                [b'\\', b'u', ref rest..] => 
+                [b, ref rest..] if b == quote => {source = rest; break;},
+
+                //line terminators must be escaped
+                [b'\r', ..] | [b'\n', ..] => return (TokenType::Error,source),
+
+                [b'\\', ref rest..] => match Self::read_escape(rest, quote, options)
                 {
-                    let (c,rest) = Self::read_unicode_escape(rest);
-                    buffer.push(c);
-                    source = rest; 
+                    Some((c, rest)) =>
+                    {
+                        if let Some(c) = c
+                        {
+                            buffer.push(c);
+                        }
+                        source = rest;
+                    },
+                    None => return (TokenType::Error,source),
                 },
 
                 //UTF8 codepoints
@@ -188,6 +374,94 @@ impl<'src> Lex<'src> {
         (TokenType::String(buffer),source)
     }
 
+    /// Finds the end of a string without decoding it
+    /// Accepts exactly the strings that read_string accepts
+    ///
+    /// The limit applies to the string as written, escapes included
+    fn scan_string(source : &'src [u8], quote : u8, options : ParserOptions, limit : usize) -> (TokenType,&'src [u8])
+    {
+        let start = source;
+        let mut source = source;
+        loop
+        {
+            if start.len() - source.len() > limit
+            {
+                return (TokenType::LongString,source);
+            }
+            match *source
+            {
+                [b, ref rest..] if b == quote => return (TokenType::String(String::new()),rest),
+                [b'\r', ..] | [b'\n', ..] => return (TokenType::Error,source),
+                [b'\\', ref rest..] => match Self::read_escape(rest, quote, options)
+                {
+                    Some((_, rest)) => source = rest,
+                    None => return (TokenType::Error,source),
+                },
+                [_, ref rest..] => source = rest,
+                [ref rest..] => return (TokenType::Error,rest),
+            }
+        }
+    }
+
+    /// Reads an escape sequence of a string closed by `quote`, after the '\\'
+    ///
+    /// Returns the character it stands for, nothing for a line continuation,
+    /// or None if `options` do not allow it. Both read_string and scan_string
+    /// go through here, so they accept the same strings
+    fn read_escape(source : &'src [u8], quote : u8, options : ParserOptions) -> Option<(Option<char>, &'src [u8])>
+    {
+        let (c, rest) = match *source
+        {
+            [b'"', ref rest..] => ('"', rest),
+            [b'\'', ref rest..] if quote == b'\'' || options.extended_escapes => ('\'', rest),
+            [b'\\', ref rest..] => ('\\', rest),
+            [b'/', ref rest..] => ('/', rest),
+            [b'b', ref rest..] => ('\x08', rest),
+            [b'f', ref rest..] => ('\x0c', rest),
+            [b'n', ref rest..] => ('\n', rest),
+            [b'r', ref rest..] => ('\r', rest),
+            [b't', ref rest..] => ('\t', rest),
+            [b'u', ref rest..] => Self::read_unicode_escape(rest),
+
+            //line continuations, which add nothing to the string
+            [b'\r', b'\n', ref rest..] if options.line_continuations => return Some((None, rest)),
+            [b'\r', ref rest..] | [b'\n', ref rest..] if options.line_continuations => return Some((None, rest)),
+            [0xE2, 0x80, 0xA8, ref rest..] | [0xE2, 0x80, 0xA9, ref rest..] if options.line_continuations =>
+            {
+                return Some((None, rest));
+            },
+
+            //ECMAScript escape sequences
+            [b'v', ref rest..] if options.extended_escapes => ('\x0b', rest),
+            [b'0', b'0'..=b'9', ..] | [b'1'..=b'9', ..] => return None,
+            [b'0', ref rest..] if options.extended_escapes => ('\0', rest),
+            [b'x', ref rest..] if options.extended_escapes => Self::read_hex_escape(rest)?,
+            // any other escaped character stands for itself
+            [b, ..] if options.extended_escapes && b != b'\r' && b != b'\n' =>
+            {
+                let c = unsafe {str::from_utf8_unchecked(source)}.chars().next()?;
+                (c, &source[c.len_utf8()..])
+            },
+            _ => return None,
+        };
+        Some((Some(c), rest))
+    }
+
+    /// Reads the two hex digits of an escape sequence after '\x'
+    fn read_hex_escape(source : &'src [u8]) -> Option<(char, &'src [u8])>
+    {
+        let digit = |b : u8| (b as char).to_digit(16);
+        match *source
+        {
+            [high, low, ref rest..] => match (digit(high), digit(low))
+            {
+                (Some(high), Some(low)) => Some((char::from((high * 16 + low) as u8), rest)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
     /// Reads a Unicode escape sequence, sequence after '\u'
     /// Reads two escape sequences, if the first is leading surrogate
     /// Invalid or incomplete sequences are replaced by 
@@ -253,48 +527,150 @@ impl<'src> Lex<'src> {
         (Some(unit as u32),source)
     }
 
-    fn read_number(mut source : &'src [u8]) -> (TokenType, &'src [u8])
+    /// Reads a bare word: a literal, or an identifier name if unquoted keys are enabled
+    fn read_identifier(source : &'src [u8], skip : bool, options : ParserOptions) -> (TokenType, &'src [u8])
+    {
+        let length = Self::identifier_length(source);
+        if length == 0
+        {
+            // not an identifier, consume the whole character
+            let length = match source[0] {0x00..=0x7F => 1, 0xC0..=0xDF => 2, 0xE0..=0xEF => 3, _ => 4};
+            return (TokenType::Error, &source[length.min(source.len())..]);
+        }
+
+        let (word, rest) = source.split_at(length);
+        let token_type = match word
+        {
+            b"true" => TokenType::Bool(true),
+            b"false" => TokenType::Bool(false),
+            b"null" => TokenType::Null,
+            b"NaN" if options.nan_infinity => TokenType::Number(f64::NAN),
+            b"Infinity" if options.nan_infinity => TokenType::Number(f64::INFINITY),
+            _ if options.unquoted_keys && skip => TokenType::Identifier(String::new()),
+            _ if options.unquoted_keys => TokenType::Identifier(Self::identifier_name(word)),
+            _ => TokenType::Error,
+        };
+        (token_type, rest)
+    }
+
+    /// Length in bytes of the ECMAScript identifier name at the start of `source`,
+    /// which must begin at a character boundary
+    ///
+    /// The name may contain Unicode escape sequences `\\uXXXX`
+    pub(crate) fn identifier_length(source : &'src [u8]) -> usize
     {
+        let text = unsafe {str::from_utf8_unchecked(source)};
+        let mut length = 0;
+        while let Some(c) = text[length..].chars().next()
+        {
+            let (c, width) = match c
+            {
+                '\\' => match Self::identifier_escape(&source[length..])
+                {
+                    Some(c) => (c, 6),
+                    None => break,
+                },
+                c => (c, c.len_utf8()),
+            };
+            let is_part = c.is_alphabetic() || c == '_' || c == '$'
+                || (length > 0 && (c.is_alphanumeric() || c == '\u{200C}' || c == '\u{200D}'));
+            if !is_part
+            {
+                break;
+            }
+            length += width;
+        }
+        length
+    }
+
+    /// Reads a Unicode escape sequence in an identifier name, '\\u' and four hex digits
+    fn identifier_escape(source : &'src [u8]) -> Option<char>
+    {
+        match *source
+        {
+            [b'\\', b'u', ref rest..] => Self::read_unit(rest).0.and_then(char::from_u32),
+            _ => None,
+        }
+    }
+
+    /// Decodes an identifier name read by identifier_length
+    fn identifier_name(mut word : &'src [u8]) -> String
+    {
+        let mut name = String::with_capacity(word.len());
+        while !word.is_empty()
+        {
+            match Self::identifier_escape(word)
+            {
+                Some(c) =>
+                {
+                    name.push(c);
+                    word = &word[6..];
+                },
+                None =>
+                {
+                    let c = unsafe {str::from_utf8_unchecked(word)}.chars().next().unwrap_or('\u{FFFD}');
+                    name.push(c);
+                    word = &word[c.len_utf8()..];
+                },
+            }
+        }
+        name
+    }
+
+    fn read_number(mut source : &'src [u8], options : ParserOptions) -> (TokenType, &'src [u8])
+    {
+        let start = source;
         let is_positive = match *source
         {
             [b'-', ref rest..] => {source = rest; false},
+            [b'+', ref rest..] if options.leading_plus => {source = rest; true},
             _ => true,
         };
+        let sign = if is_positive {1.0} else {-1.0};
 
-        let mut decimal_part : u64;
-        match *source
+        if options.hex_numbers
         {
-            [b'0', ref rest..] => 
+            match *source
             {
-                source = rest;
-                decimal_part = 0;
-            },
-            [b @ b'1'..=b'9', ref rest..] => 
+                [b'0', b'x', ref rest..] | [b'0', b'X', ref rest..] => return Self::read_hex(rest, sign),
+                _ => {},
+            }
+        }
+
+        if options.nan_infinity
+        {
+            let length = Self::identifier_length(source);
+            match &source[..length]
+            {
+                b"Infinity" => return (TokenType::Number(sign * f64::INFINITY), &source[length..]),
+                b"NaN" => return (TokenType::Number(f64::NAN), &source[length..]),
+                _ => {},
+            }
+        }
+
+        match *source
+        {
+            [b'.', b'0'..=b'9', ..] if options.leading_decimal_point => {},
+            [b'0', ref rest..] => source = rest,
+            [b'1'..=b'9', ref rest..] => 
             {
                 source = rest;
-                decimal_part = (b - b'0') as u64;
-                while let [d @ b'0'..=b'9', ref rest..] = *source {
+                while let [b'0'..=b'9', ref rest..] = *source {
                     source = rest;
-                    let d = (d - b'0') as u64; 
-                    decimal_part = decimal_part * 10 + d;
                 }
             },
             _ => return (TokenType::Error,source),
         }
 
-        let mut exp : i32 = 0;
         if let [b'.', ref rest..] = *source
         {
             source = rest;
             let mut any_digits = false;
-            while let [d @ b'0'..=b'9', ref rest..] = *source {
-                let d = (d - b'0') as u64;
-                decimal_part = decimal_part * 10 + d;
-                exp -= 1;
+            while let [b'0'..=b'9', ref rest..] = *source {
                 any_digits = true;
                 source = rest;
             }
-            if !any_digits
+            if !any_digits && !options.trailing_decimal_point
             {
                 return (TokenType::Error,source);
             }
@@ -309,50 +685,52 @@ impl<'src> Lex<'src> {
         if has_exp
         {
             source = rest;
-            let is_pos_exp = match *source
+            if let [b'+', ref rest..] | [b'-', ref rest..] = *source
             {
-                [b'+', ref rest..] => {source = rest; true},
-                [b'-', ref rest..] => {source = rest; false},
-                _ => true,
-            };
+                source = rest;
+            }
 
-            let mut exlicit_exp = 0;
             let mut any_digits = false;
-            while let [d @ b'0'..=b'9', ref rest..] = *source
+            while let [b'0'..=b'9', ref rest..] = *source
             {
                 source = rest;
                 any_digits = true;
-                let d = (d - b'0') as i32;
-                exlicit_exp = 10*exlicit_exp + d;
             }
             if !any_digits
             {
                 return (TokenType::Error,source);
             }
-            if is_pos_exp
-            {
-                exp += exlicit_exp;
-            }
-            else 
-            {
-                exp -= exlicit_exp;
-            }
         }
 
-           let mut val = decimal_part as f64;
-           for _ in 0..i32::abs(exp)
-           {
-               if exp > 0
-               {
-                   val *= 10.0;
-               }
-               else 
-               {
-                   val /= 10.0;
-               }
-           }
-           if !is_positive {val = -val;}
-
+        // the text is known to be a number, which the standard library
+        // converts to the nearest double
+        let text = unsafe {str::from_utf8_unchecked(&start[..start.len() - source.len()])};
+        let val = text.parse::<f64>().unwrap_or(0.0);
         (TokenType::Number(val),source)
     }
+
+    /// Reads the digits of a hexadecimal integer, after '0x'
+    fn read_hex(mut source : &'src [u8], sign : f64) -> (TokenType, &'src [u8])
+    {
+        let mut val = 0.0;
+        let mut any_digits = false;
+        loop
+        {
+            let digit = match *source
+            {
+                [b @ b'0'..=b'9', ..] => b - b'0',
+                [b @ b'A'..=b'F', ..] => b - b'A' + 10,
+                [b @ b'a'..=b'f', ..] => b - b'a' + 10,
+                _ => break,
+            };
+            val = val * 16.0 + digit as f64;
+            any_digits = true;
+            source = &source[1..];
+        }
+        if !any_digits
+        {
+            return (TokenType::Error,source);
+        }
+        (TokenType::Number(sign * val),source)
+    }
 }
@@ -3,6 +3,24 @@
 pub(crate) mod lex;
 mod test_lex;
 pub mod json;
+pub mod map;
+pub mod raw;
 pub mod parse_error;
+pub mod limits;
+pub mod options;
 pub mod parser;
+pub mod ser;
+pub mod writer;
+pub mod pointer;
+pub mod source_map;
+pub mod extract;
+pub mod stream;
+pub mod filter;
+pub mod jsonc;
+pub mod json5;
+pub mod ijson;
+pub mod jcs;
+pub mod hash;
+pub mod cst;
 mod test_syntax;
+mod test_stream;
@@ -0,0 +1,92 @@
+/// Resource limits enforced by `JsonParser`
+///
+/// Every limit is off until set. Exceeding one fails the parse
+/// with the matching `ErrorKind`.
+#[derive(Default)]
+pub struct ParserLimits
+{
+    pub(crate) max_depth : Option<usize>,
+    pub(crate) max_input_size : Option<usize>,
+    pub(crate) max_string_length : Option<usize>,
+    pub(crate) max_object_members : Option<usize>,
+    pub(crate) max_array_length : Option<usize>,
+    pub(crate) max_allocated_bytes : Option<usize>,
+    pub(crate) cancel : Option<Box<dyn Fn() -> bool>>,
+}
+
+impl ParserLimits
+{
+    pub fn new() -> ParserLimits
+    {
+        ParserLimits::default()
+    }
+
+    /// Maximum nesting of objects and arrays
+    pub fn max_depth(mut self, depth : usize) -> ParserLimits
+    {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Maximum length of the whole source, in bytes
+    pub fn max_input_size(mut self, bytes : usize) -> ParserLimits
+    {
+        self.max_input_size = Some(bytes);
+        self
+    }
+
+    /// Maximum length of a single string or key, in bytes
+    ///
+    /// Decoded strings are measured after decoding, skipped ones as written.
+    /// Reading stops at the limit, so an overlong string is never allocated.
+    pub fn max_string_length(mut self, bytes : usize) -> ParserLimits
+    {
+        self.max_string_length = Some(bytes);
+        self
+    }
+
+    /// Maximum number of members in a single object
+    pub fn max_object_members(mut self, members : usize) -> ParserLimits
+    {
+        self.max_object_members = Some(members);
+        self
+    }
+
+    /// Maximum number of elements in a single array
+    pub fn max_array_length(mut self, elements : usize) -> ParserLimits
+    {
+        self.max_array_length = Some(elements);
+        self
+    }
+
+    /// Maximum number of bytes allocated for the parsed document
+    ///
+    /// Counts the capacity of every string, key and raw value, the copy
+    /// of each key in its object's index, and the capacity of objects
+    /// and arrays. Bookkeeping while parsing is not counted.
+    pub fn max_allocated_bytes(mut self, bytes : usize) -> ParserLimits
+    {
+        self.max_allocated_bytes = Some(bytes);
+        self
+    }
+
+    /// Stop parsing as soon as `cancel` returns true
+    ///
+    /// The hook is polled at every object and array
+    /// and periodically between tokens
+    pub fn cancel_when<F>(mut self, cancel : F) -> ParserLimits
+        where F : Fn() -> bool + 'static
+    {
+        self.cancel = Some(Box::new(cancel));
+        self
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool
+    {
+        match self.cancel
+        {
+            Some(ref cancel) => cancel(),
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,454 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::iter::FromIterator;
+use std::ops::Index;
+use std::{fmt, mem, slice, vec};
+
+/// A map from keys to values that keeps the order in which keys were
+/// first inserted
+///
+/// Lookups go through a hash index, iteration follows insertion order,
+/// so an object's members come out in the order they were parsed.
+/// Two maps are equal if they have the same entries in any order.
+#[derive(Clone)]
+pub struct JsonMap<V>
+{
+    entries : Vec<(String, V)>,
+    index : HashMap<String, usize>,
+}
+
+/// Iterator over the entries of a `JsonMap`, in order
+pub struct Iter<'a, V : 'a>(slice::Iter<'a, (String, V)>);
+
+/// Iterator over the entries of a `JsonMap` with mutable values, in order
+pub struct IterMut<'a, V : 'a>(slice::IterMut<'a, (String, V)>);
+
+/// A key of a `JsonMap`, with its value if it has one
+pub enum Entry<'a, V : 'a>
+{
+    Occupied(OccupiedEntry<'a, V>),
+    Vacant(VacantEntry<'a, V>),
+}
+
+/// A key of a `JsonMap` that has a value
+pub struct OccupiedEntry<'a, V : 'a>
+{
+    map : &'a mut JsonMap<V>,
+    position : usize,
+}
+
+/// A key that is not in a `JsonMap` yet
+pub struct VacantEntry<'a, V : 'a>
+{
+    map : &'a mut JsonMap<V>,
+    key : String,
+}
+
+impl<V> JsonMap<V>
+{
+    pub fn new() -> JsonMap<V>
+    {
+        JsonMap{entries : Vec::new(), index : HashMap::new()}
+    }
+
+    pub fn with_capacity(capacity : usize) -> JsonMap<V>
+    {
+        JsonMap{entries : Vec::with_capacity(capacity), index : HashMap::with_capacity(capacity)}
+    }
+
+    pub fn len(&self) -> usize
+    {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, key : &str) -> Option<&V>
+    {
+        self.index.get(key).map(|&i| &self.entries[i].1)
+    }
+
+    pub fn get_mut(&mut self, key : &str) -> Option<&mut V>
+    {
+        match self.index.get(key)
+        {
+            Some(&i) => Some(&mut self.entries[i].1),
+            None => None,
+        }
+    }
+
+    pub fn contains_key(&self, key : &str) -> bool
+    {
+        self.index.contains_key(key)
+    }
+
+    /// Position of `key` in insertion order
+    pub fn position(&self, key : &str) -> Option<usize>
+    {
+        self.index.get(key).cloned()
+    }
+
+    /// Insert a value, returning the one it replaces
+    ///
+    /// A new key goes last, a key already present keeps its position
+    pub fn insert(&mut self, key : String, value : V) -> Option<V>
+    {
+        if let Some(&i) = self.index.get(&key)
+        {
+            return Some(mem::replace(&mut self.entries[i].1, value));
+        }
+        self.index.insert(key.clone(), self.entries.len());
+        self.entries.push((key, value));
+        None
+    }
+
+    /// The entry of `key`, for looking up and inserting with one lookup
+    pub fn entry(&mut self, key : String) -> Entry<V>
+    {
+        match self.index.get(&key)
+        {
+            Some(&position) => Entry::Occupied(OccupiedEntry{map : self, position}),
+            None => Entry::Vacant(VacantEntry{map : self, key}),
+        }
+    }
+
+    /// Remove a key, keeping the order of the others
+    pub fn remove(&mut self, key : &str) -> Option<V>
+    {
+        let i = self.index.remove(key)?;
+        let (_, value) = self.entries.remove(i);
+        self.reindex(i);
+        Some(value)
+    }
+
+    /// Keep only the entries for which `keep` returns true
+    pub fn retain<F>(&mut self, mut keep : F)
+        where F : FnMut(&str, &mut V) -> bool
+    {
+        // kept entries are swapped down to the front, in order
+        let mut kept = 0;
+        for i in 0..self.entries.len()
+        {
+            let (ref key, ref mut value) = self.entries[i];
+            if keep(key, value)
+            {
+                self.entries.swap(kept, i);
+                if let Some(position) = self.index.get_mut(self.entries[kept].0.as_str())
+                {
+                    *position = kept;
+                }
+                kept += 1;
+            }
+            else
+            {
+                self.index.remove(key.as_str());
+            }
+        }
+        self.entries.truncate(kept);
+    }
+
+    /// Sort the entries by key, as a `BTreeMap` would order them
+    pub fn sort_keys(&mut self)
+    {
+        self.sort_by(|a, _, b, _| a.cmp(b));
+    }
+
+    /// Reorder the entries with a comparison of keys and values
+    pub fn sort_by<F>(&mut self, mut compare : F)
+        where F : FnMut(&str, &V, &str, &V) -> Ordering
+    {
+        self.entries.sort_by(|a, b| compare(&a.0, &a.1, &b.0, &b.1));
+        self.reindex(0);
+    }
+
+    pub fn clear(&mut self)
+    {
+        self.entries.clear();
+        self.index.clear();
+    }
+
+    /// Remove every entry, yielding them in order
+    pub fn drain(&mut self) -> vec::Drain<(String, V)>
+    {
+        self.index.clear();
+        self.entries.drain(..)
+    }
+
+    pub fn iter(&self) -> Iter<V>
+    {
+        Iter(self.entries.iter())
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<V>
+    {
+        IterMut(self.entries.iter_mut())
+    }
+
+    pub fn keys<'a>(&'a self) -> impl Iterator<Item = &'a String> + 'a
+    {
+        self.entries.iter().map(|entry| &entry.0)
+    }
+
+    pub fn values<'a>(&'a self) -> impl Iterator<Item = &'a V> + 'a
+    {
+        self.entries.iter().map(|entry| &entry.1)
+    }
+
+    pub fn values_mut<'a>(&'a mut self) -> impl Iterator<Item = &'a mut V> + 'a
+    {
+        self.entries.iter_mut().map(|entry| &mut entry.1)
+    }
+
+    /// Bytes allocated for the entries and the index, not counting
+    /// what the keys and values allocate themselves
+    pub(crate) fn allocated(&self) -> usize
+    {
+        // a hash table slot also takes a control byte
+        self.entries.capacity() * mem::size_of::<(String, V)>() + self.index.capacity() * (mem::size_of::<(String, usize)>() + 1)
+    }
+
+    /// Update the indexed positions of the entries from `from` on,
+    /// after entries before them moved
+    fn reindex(&mut self, from : usize)
+    {
+        for (i, entry) in self.entries.iter().enumerate().skip(from)
+        {
+            if let Some(position) = self.index.get_mut(entry.0.as_str())
+            {
+                *position = i;
+            }
+        }
+    }
+}
+
+impl<'a, V> Entry<'a, V>
+{
+    pub fn key(&self) -> &str
+    {
+        match *self
+        {
+            Entry::Occupied(ref entry) => entry.key(),
+            Entry::Vacant(ref entry) => entry.key(),
+        }
+    }
+
+    /// The value, inserting `default` if there is none
+    pub fn or_insert(self, default : V) -> &'a mut V
+    {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with<F>(self, default : F) -> &'a mut V
+        where F : FnOnce() -> V
+    {
+        match self
+        {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Run `modify` on the value, if there is one
+    pub fn and_modify<F>(mut self, modify : F) -> Entry<'a, V>
+        where F : FnOnce(&mut V)
+    {
+        if let Entry::Occupied(ref mut entry) = self
+        {
+            modify(entry.get_mut());
+        }
+        self
+    }
+}
+
+impl<'a, V> OccupiedEntry<'a, V>
+{
+    pub fn key(&self) -> &str
+    {
+        &self.map.entries[self.position].0
+    }
+
+    pub fn get(&self) -> &V
+    {
+        &self.map.entries[self.position].1
+    }
+
+    pub fn get_mut(&mut self) -> &mut V
+    {
+        &mut self.map.entries[self.position].1
+    }
+
+    pub fn into_mut(self) -> &'a mut V
+    {
+        &mut self.map.entries[self.position].1
+    }
+
+    /// Replace the value, returning the old one
+    pub fn insert(&mut self, value : V) -> V
+    {
+        mem::replace(self.get_mut(), value)
+    }
+
+    /// Remove the key, keeping the order of the others
+    pub fn remove(self) -> V
+    {
+        let (key, value) = self.map.entries.remove(self.position);
+        self.map.index.remove(&key);
+        self.map.reindex(self.position);
+        value
+    }
+}
+
+impl<'a, V> VacantEntry<'a, V>
+{
+    pub fn key(&self) -> &str
+    {
+        &self.key
+    }
+
+    /// Insert the value, after every other key
+    pub fn insert(self, value : V) -> &'a mut V
+    {
+        let position = self.map.entries.len();
+        self.map.index.insert(self.key.clone(), position);
+        self.map.entries.push((self.key, value));
+        &mut self.map.entries[position].1
+    }
+}
+
+impl<V> Default for JsonMap<V>
+{
+    fn default() -> JsonMap<V>
+    {
+        JsonMap::new()
+    }
+}
+
+impl<V : PartialEq> PartialEq for JsonMap<V>
+{
+    fn eq(&self, other : &JsonMap<V>) -> bool
+    {
+        self.len() == other.len() && self.iter().all(|(key, value)| other.get(key) == Some(value))
+    }
+}
+
+impl<V : fmt::Debug> fmt::Debug for JsonMap<V>
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, V> Index<&'a str> for JsonMap<V>
+{
+    type Output = V;
+
+    fn index(&self, key : &str) -> &V
+    {
+        self.get(key).expect("no entry found for key")
+    }
+}
+
+impl<'a, V> Iterator for Iter<'a, V>
+{
+    type Item = (&'a String, &'a V);
+
+    fn next(&mut self) -> Option<(&'a String, &'a V)>
+    {
+        self.0.next().map(|entry| (&entry.0, &entry.1))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>)
+    {
+        self.0.size_hint()
+    }
+}
+
+impl<'a, V> DoubleEndedIterator for Iter<'a, V>
+{
+    fn next_back(&mut self) -> Option<(&'a String, &'a V)>
+    {
+        self.0.next_back().map(|entry| (&entry.0, &entry.1))
+    }
+}
+
+impl<'a, V> ExactSizeIterator for Iter<'a, V> {}
+
+impl<'a, V> Iterator for IterMut<'a, V>
+{
+    type Item = (&'a String, &'a mut V);
+
+    fn next(&mut self) -> Option<(&'a String, &'a mut V)>
+    {
+        self.0.next().map(|entry| (&entry.0, &mut entry.1))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>)
+    {
+        self.0.size_hint()
+    }
+}
+
+impl<'a, V> DoubleEndedIterator for IterMut<'a, V>
+{
+    fn next_back(&mut self) -> Option<(&'a String, &'a mut V)>
+    {
+        self.0.next_back().map(|entry| (&entry.0, &mut entry.1))
+    }
+}
+
+impl<'a, V> ExactSizeIterator for IterMut<'a, V> {}
+
+impl<'a, V> IntoIterator for &'a JsonMap<V>
+{
+    type Item = (&'a String, &'a V);
+    type IntoIter = Iter<'a, V>;
+
+    fn into_iter(self) -> Iter<'a, V>
+    {
+        self.iter()
+    }
+}
+
+impl<'a, V> IntoIterator for &'a mut JsonMap<V>
+{
+    type Item = (&'a String, &'a mut V);
+    type IntoIter = IterMut<'a, V>;
+
+    fn into_iter(self) -> IterMut<'a, V>
+    {
+        self.iter_mut()
+    }
+}
+
+impl<V> IntoIterator for JsonMap<V>
+{
+    type Item = (String, V);
+    type IntoIter = vec::IntoIter<(String, V)>;
+
+    fn into_iter(self) -> vec::IntoIter<(String, V)>
+    {
+        self.entries.into_iter()
+    }
+}
+
+impl<V> FromIterator<(String, V)> for JsonMap<V>
+{
+    fn from_iter<I : IntoIterator<Item = (String, V)>>(entries : I) -> JsonMap<V>
+    {
+        let mut map = JsonMap::new();
+        map.extend(entries);
+        map
+    }
+}
+
+impl<V> Extend<(String, V)> for JsonMap<V>
+{
+    fn extend<I : IntoIterator<Item = (String, V)>>(&mut self, entries : I)
+    {
+        for (key, value) in entries
+        {
+            self.insert(key, value);
+        }
+    }
+}
@@ -0,0 +1,171 @@
+/// Syntax extensions accepted by `JsonParser`, and how it treats
+/// objects that repeat a key
+///
+/// Every extension is off by default, which is strict RFC 8259 JSON.
+/// Each switch enables one extension and is independent of the others.
+#[derive(Debug,Default,Clone,Copy,PartialEq)]
+pub struct ParserOptions
+{
+    pub(crate) duplicate_keys : DuplicateKeys,
+    pub(crate) trailing_commas : bool,
+    pub(crate) comments : bool,
+    pub(crate) single_quotes : bool,
+    pub(crate) unquoted_keys : bool,
+    pub(crate) nan_infinity : bool,
+    pub(crate) leading_plus : bool,
+    pub(crate) leading_decimal_point : bool,
+    pub(crate) hex_numbers : bool,
+    pub(crate) trailing_decimal_point : bool,
+    pub(crate) line_continuations : bool,
+    pub(crate) extended_escapes : bool,
+    pub(crate) extended_whitespace : bool,
+}
+
+/// What to do when an object repeats a key
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum DuplicateKeys
+{
+    /// Fail with `ErrorKind::DuplicateKey`, giving both occurrences
+    Error,
+    /// Keep the value of the first occurrence
+    FirstWins,
+    /// Keep the value of the last occurrence
+    LastWins,
+    /// Keep the last value in the document and every value of
+    /// a repeated key in `JsonParser::duplicates`
+    KeepAll,
+}
+
+impl Default for DuplicateKeys
+{
+    fn default() -> DuplicateKeys
+    {
+        DuplicateKeys::LastWins
+    }
+}
+
+impl ParserOptions
+{
+    /// Strict RFC 8259 JSON, with no extension enabled
+    pub fn new() -> ParserOptions
+    {
+        ParserOptions::default()
+    }
+
+    /// JSON with comments, as read by VS Code for `settings.json` and
+    /// `tsconfig.json`: comments and trailing commas
+    pub fn jsonc() -> ParserOptions
+    {
+        ParserOptions::new().comments(true).trailing_commas(true)
+    }
+
+    /// JSON5: every extension
+    pub fn json5() -> ParserOptions
+    {
+        ParserOptions::new().trailing_commas(true).comments(true).single_quotes(true).unquoted_keys(true)
+            .nan_infinity(true).leading_plus(true).leading_decimal_point(true).hex_numbers(true)
+            .trailing_decimal_point(true).line_continuations(true).extended_escapes(true)
+            .extended_whitespace(true)
+    }
+
+    /// A comma after the last member or element: `[1, 2,]`
+    pub fn trailing_commas(mut self, allow : bool) -> ParserOptions
+    {
+        self.trailing_commas = allow;
+        self
+    }
+
+    /// Line comments `// ...` and block comments `/* ... */` wherever whitespace is allowed
+    pub fn comments(mut self, allow : bool) -> ParserOptions
+    {
+        self.comments = allow;
+        self
+    }
+
+    /// Strings in single quotes: `'text'`, where `\'` escapes a quote
+    pub fn single_quotes(mut self, allow : bool) -> ParserOptions
+    {
+        self.single_quotes = allow;
+        self
+    }
+
+    /// Keys written as ECMAScript identifier names: `{key: 1}`,
+    /// which may contain escapes such as `\u0041`
+    pub fn unquoted_keys(mut self, allow : bool) -> ParserOptions
+    {
+        self.unquoted_keys = allow;
+        self
+    }
+
+    /// The literals `NaN` and `Infinity`, optionally signed
+    pub fn nan_infinity(mut self, allow : bool) -> ParserOptions
+    {
+        self.nan_infinity = allow;
+        self
+    }
+
+    /// Numbers with an explicit plus sign: `+1`
+    pub fn leading_plus(mut self, allow : bool) -> ParserOptions
+    {
+        self.leading_plus = allow;
+        self
+    }
+
+    /// Numbers without an integer part: `.5`
+    pub fn leading_decimal_point(mut self, allow : bool) -> ParserOptions
+    {
+        self.leading_decimal_point = allow;
+        self
+    }
+
+    /// Hexadecimal integers: `0x1F`
+    pub fn hex_numbers(mut self, allow : bool) -> ParserOptions
+    {
+        self.hex_numbers = allow;
+        self
+    }
+
+    /// Numbers without a fractional part after the decimal point: `5.`
+    pub fn trailing_decimal_point(mut self, allow : bool) -> ParserOptions
+    {
+        self.trailing_decimal_point = allow;
+        self
+    }
+
+    /// A backslash before a line break continues a string on the next line
+    pub fn line_continuations(mut self, allow : bool) -> ParserOptions
+    {
+        self.line_continuations = allow;
+        self
+    }
+
+    /// The ECMAScript escapes `\x41`, `\v`, `\0` and `\'`,
+    /// and any other escaped character standing for itself, except the
+    /// digits 1-9 and a `\0` followed by a digit
+    pub fn extended_escapes(mut self, allow : bool) -> ParserOptions
+    {
+        self.extended_escapes = allow;
+        self
+    }
+
+    /// ECMAScript whitespace besides the JSON one: vertical tab, form feed,
+    /// no-break space, byte order mark, line separators and Unicode spaces
+    pub fn extended_whitespace(mut self, allow : bool) -> ParserOptions
+    {
+        self.extended_whitespace = allow;
+        self
+    }
+
+    /// How to treat an object that repeats a key, `LastWins` by default
+    pub fn duplicate_keys(mut self, policy : DuplicateKeys) -> ParserOptions
+    {
+        self.duplicate_keys = policy;
+        self
+    }
+
+    /// True if the lexer reads bare words as identifiers
+    pub(crate) fn identifiers(&self) -> bool
+    {
+        self.unquoted_keys || self.nan_infinity
+    }
+}
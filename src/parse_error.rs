@@ -1,18 +1,83 @@
 use lex::Token;
 use std::{fmt,result};
 
-/// Syntax error - An unexpected token
+/// Syntax error - An unexpected token, or a parser limit exceeded at a token
 pub struct ParseError<'src>
 {
     pub(crate) token : Token<'src>,
+    pub(crate) kind : ErrorKind,
+}
+
+/// What went wrong at the token of a `ParseError`
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum ErrorKind
+{
+    UnexpectedToken,
+    /// Objects and arrays nested deeper than the limit
+    DepthLimitExceeded(usize),
+    /// The source is longer than the limit, in bytes
+    InputSizeLimitExceeded(usize),
+    /// A string longer than the limit, in bytes
+    StringLengthLimitExceeded(usize),
+    /// An object with more members than the limit
+    MemberCountLimitExceeded(usize),
+    /// An array with more elements than the limit
+    ArrayLengthLimitExceeded(usize),
+    /// Building the document would allocate more bytes than the limit
+    AllocationLimitExceeded(usize),
+    /// The cancellation hook asked the parser to stop
+    Cancelled,
+    /// An object repeats a key, at these byte offsets of the
+    /// first and second occurrence
+    DuplicateKey{first : usize, second : usize},
+}
+
+impl<'src> ParseError<'src>
+{
+    pub fn kind(&self) -> ErrorKind
+    {
+        self.kind
+    }
+
+    /// The source text of the token at which the error occurred
+    pub fn span(&self) -> &'src str
+    {
+        self.token.span
+    }
 }
 
 pub type Result<'src,T> = result::Result<T,ParseError<'src>>;
 
+impl fmt::Display for ErrorKind
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self
+        {
+            ErrorKind::UnexpectedToken => write!(f, "unexpected token"),
+            ErrorKind::DepthLimitExceeded(limit) => write!(f, "nesting deeper than {} levels", limit),
+            ErrorKind::InputSizeLimitExceeded(limit) => write!(f, "input longer than {} bytes", limit),
+            ErrorKind::StringLengthLimitExceeded(limit) => write!(f, "string longer than {} bytes", limit),
+            ErrorKind::MemberCountLimitExceeded(limit) => write!(f, "object with more than {} members", limit),
+            ErrorKind::ArrayLengthLimitExceeded(limit) => write!(f, "array with more than {} elements", limit),
+            ErrorKind::AllocationLimitExceeded(limit) => write!(f, "document needs more than {} bytes", limit),
+            ErrorKind::Cancelled => write!(f, "parsing cancelled"),
+            ErrorKind::DuplicateKey{first, second} =>
+            {
+                write!(f, "duplicate key at byte {}, first at byte {}", second, first)
+            },
+        }
+    }
+}
+
 impl<'src> fmt::Debug for ParseError<'src> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result 
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
     {
-        write!(f, "Syntax error: unexpected token {:?}", self.token)?;
+        match self.kind
+        {
+            ErrorKind::UnexpectedToken => write!(f, "Syntax error: unexpected token {:?}", self.token)?,
+            ErrorKind::DuplicateKey{..} => write!(f, "Syntax error: {} {:?}", self.kind, self.token)?,
+            kind => write!(f, "Limit exceeded: {} at {:?}", kind, self.token)?,
+        }
         Ok(())
     }
 }
@@ -1,6 +1,18 @@
 use lex::{Lex,Token,TokenType};
 use json;
-use parse_error::{ParseError,Result};
+use filter::FieldFilter;
+use limits::ParserLimits;
+use options::{DuplicateKeys,ParserOptions};
+use parse_error::{ErrorKind,ParseError,Result};
+use pointer::{JsonPointer,Step};
+use raw::{RawCapture,RawValue};
+use source_map::{SourceMap,SpanRecorder};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::mem;
+
+/// How many tokens are read between polls of the cancellation hook
+const CANCEL_INTERVAL : usize = 1024;
 
 ///A JSON parser
 ///
@@ -12,395 +24,1035 @@ use parse_error::{ParseError,Result};
 /// 
 /// ARRAY = '[' ']' | '[' ELEMENTS ']'
 /// ELEMENTS = VALUE | ELEMENTS ',' VALUE
-
+///
+/// The extensions enabled by `ParserOptions` relax this grammar,
+/// which is strict RFC 8259 JSON by default
+///
+/// Objects and arrays being built are kept on an explicit stack
+/// rather than in native recursion, so any nesting depth parses,
+/// limited only by memory and `ParserLimits::max_depth`
 pub struct JsonParser<'src>
 {
+    source : &'src str,
     lexer : Lex<'src>,
+    filter : Option<FieldFilter>,
+    capture : Option<RawCapture>,
+    limits : Option<ParserLimits>,
+    pending : Option<ErrorKind>,
+    tokens : usize,
+    allocated : usize,
+    depth : usize,
+    stats : ValidationStats,
+    duplicates : Vec<DuplicateMember>,
+    spans : Option<SpanRecorder<'src>>,
 }
 
-struct Value(json::JsonValue);
-struct Object(json::JsonObject);
-struct Array(json::JsonArray);
-struct Members(json::JsonObject);
-struct Member((String, json::JsonValue));
-struct Elements(json::JsonArray);
+/// A repeated key and all its values, kept by `DuplicateKeys::KeepAll`
+#[derive(Debug,Clone,PartialEq)]
+pub struct DuplicateMember
+{
+    pub path : JsonPointer,
+    /// The byte offset of the key and the value of each occurrence,
+    /// in source order; the last value is also the one in the document
+    pub values : Vec<(usize, json::JsonValue)>,
+}
 
-enum Either<A,B>
+/// Statistics about a document gathered by `JsonParser::validate`
+#[derive(Debug,Default,Clone,Copy,PartialEq)]
+pub struct ValidationStats
 {
-    First(A),
-    Second(B),
+    /// The deepest nesting of objects and arrays, 0 for a scalar document
+    pub max_depth : usize,
+    /// The number of values, including containers and the root
+    pub values : usize,
 }
 
-impl<'src> JsonParser<'src> {
-    pub fn new(source : &'src str) -> JsonParser
-    {
-        JsonParser { lexer : Lex::new(source) }
-    }
+/// An object or array being built, waiting for its next value
+///
+/// Containers marked `filtered` have their members checked against
+/// the field filter, and the tokens inside them are read without
+/// decoding strings until a member is known to be kept
+enum Frame<'src>
+{
+    // OBJECT = '{' MEMBERS * '}', `key` is the key of the member being built,
+    // `members` what is known of the members so far
+    Object{object : json::JsonObject, key : MemberKey<'src>, filtered : bool, members : Members<'src>},
+    // ARRAY = '[' ELEMENTS * ']'
+    Array{array : json::JsonArray, filtered : bool},
+}
 
-    // * is current state of the parser
-    //VALUE = * String
-    //VALUE = * Number
-    //VALUE = * Bool
-    //VALUE = * Null
-    //VALUE = * OBJECT
-    //VALUE = * ARRAY
-    //OBJECT = '{' '}'
-    //OBJECT = '{' MEMBERS '}'
-    //ARRAY = * '[' ']'
-    //ARRAY = * '[' ELEMENTS ']'
+/// The members of an object being built, dropped ones included
+///
+/// `count` is the number of members read. If the duplicate key policy is
+/// `DuplicateKeys::Error` or `DuplicateKeys::KeepAll`, `first` holds for
+/// each member in the object, by its position, the offset of its first key
+/// and, once the key repeats, the index of its record in
+/// `JsonParser::duplicates`. If it is `DuplicateKeys::Error`, `dropped`
+/// holds the keys of the members the filter drops and their offsets
+#[derive(Default)]
+struct Members<'src>
+{
+    count : usize,
+    first : Vec<(usize, Option<usize>)>,
+    dropped : HashMap<Cow<'src, str>, usize>,
+}
 
-    /// Parse a JSON Value
-    pub fn parse(&mut self) -> Result<'src, json::JsonValue>
-    {
-        let value = match self.lexer.next()
-        {
-            Token{token_type: TokenType::String(string), .. } => self.reduce_value_string(string)?,
-            Token{token_type: TokenType::Number(number),.. } => self.reduce_value_number(number)?,
-            Token{token_type: TokenType::Bool(b),.. } => self.reduce_value_bool(b)?,
-            Token{token_type: TokenType::Null,.. } => self.reduce_value_null()?,
-            Token{token_type: TokenType::LeftBrace,.. } =>
-            {
-                let o = self.parse_object()?;
-                self.reduce_value_object(o)?
-            },
-            Token{token_type: TokenType::LeftBracket,.. } =>
-            { 
-                let array = self.parse_array()?;
-                self.reduce_value_array(array)?
-            },
-            token => return Err(ParseError{token}),
-        };
+/// Keys of an object that is skipped and their offsets,
+/// recorded only if duplicate keys are errors
+type SkippedKeys<'src> = Option<HashMap<Cow<'src, str>, usize>>;
 
-        match self.lexer.next()
-        {
-            Token{token_type:  TokenType::End, ..} => 
-            {
-                let Value(value) = value;
-                Ok(value)
-            },
-            token => Err(ParseError{token}),
-        }
-    }
+/// The key of a member and the bytes where it is written
+struct MemberKey<'src>
+{
+    name : Cow<'src, str>,
+    offset : usize,
+    length : usize,
+}
+
+/// The field filter and the path it is matched against
+///
+/// `path` holds a step for every container on the stack that is
+/// subject to the filter, leading to the value being built
+struct Projection<'f, 'src>
+{
+    filter : Option<&'f FieldFilter>,
+    path : Vec<Step<'src>>,
+}
+
+/// The first token of a value to build, how it was read,
+/// and whether the value is subject to the filter
+struct Start<'src>
+{
+    token : Token<'src>,
+    skipped : bool,
+    filtered : bool,
+}
 
-    /// VALUE = String
-    fn reduce_value_string(&mut self, string : String) -> Result<'src, Value>
+/// The path of the value being built inside the containers of `frames`
+fn frames_path(frames : &[Frame]) -> JsonPointer
+{
+    let mut path = JsonPointer::root();
+    for frame in frames
     {
-        Ok(Value(json::JsonValue::JsonString(string)))
+        match *frame
+        {
+            Frame::Object{ref key, ..} => path.push(&key.name),
+            Frame::Array{ref array, ..} => path.push(&array.len().to_string()),
+        }
     }
+    path
+}
 
-    /// VALUE = Number
-    fn reduce_value_number(&mut self, number : f64) -> Result<'src, Value>
+impl<'src> JsonParser<'src> {
+    pub fn new(source : &'src str) -> JsonParser
     {
-        Ok(Value(json::JsonValue::JsonNumber(number)))
+        JsonParser
+        {
+            source,
+            lexer : Lex::new(source),
+            filter : None,
+            capture : None,
+            limits : None,
+            pending : None,
+            tokens : 0,
+            allocated : 0,
+            depth : 0,
+            stats : ValidationStats::default(),
+            duplicates : Vec::new(),
+            spans : None,
+        }
     }
 
-    /// VALUE = Bool
-    fn reduce_value_bool(&mut self, b : bool) -> Result<'src, Value>
+    /// Enforce `limits` while parsing
+    pub fn with_limits(mut self, limits : ParserLimits) -> JsonParser<'src>
     {
-        Ok(Value(json::JsonValue::JsonBool(b)))
+        self.lexer = self.lexer.with_max_string_length(limits.max_string_length);
+        self.limits = Some(limits);
+        self
     }
 
-    /// VALUE = Null
-    fn reduce_value_null(&mut self) -> Result<'src, Value>
+    /// Accept the syntax extensions enabled in `options`
+    pub fn with_options(mut self, options : ParserOptions) -> JsonParser<'src>
     {
-        Ok(Value(json::JsonValue::Null))
+        self.lexer = self.lexer.with_options(options);
+        self
     }
 
-    /// VALUE = OBJECT
-    fn reduce_value_object(&mut self, o : Object) -> Result<'src, Value>
+    /// Parse only the object members accepted by `filter`
+    pub fn with_filter(mut self, filter : FieldFilter) -> JsonParser<'src>
     {
-        let Object(object) = o;
-        Ok(Value(json::JsonValue::JsonObject(object)))
+        self.filter = Some(filter);
+        self
     }
 
-    /// VALUE = ARRAY
-    fn reduce_value_array(&mut self, array : Array) -> Result<'src, Value>
+    /// Keep the values selected by `capture` as their source text, unparsed
+    pub fn with_raw_capture(mut self, capture : RawCapture) -> JsonParser<'src>
     {
-        let Array(array) = array;
-        Ok(Value(json::JsonValue::JsonArray(array)))
+        self.capture = Some(capture);
+        self
     }
 
-    //OBJECT = '{' * MEMBERS '}'
-    //OBJECT = '{' * '}'
-    //MEMBERS = * member
-    //MEMBERS = MEMBERS * ',' member
-    //member = String ':' VALUE 
-    fn parse_object(&mut self) -> Result<'src, Object>
+    /// Parse a JSON Value
+    pub fn parse(&mut self) -> Result<'src, json::JsonValue>
     {
-        let mut members = match self.lexer.next()
+        self.reset();
+        self.check_input_size()?;
+        self.duplicates.clear();
+        let filter = self.filter.take();
+        let value = match filter
         {
-            Token{token_type: TokenType::String(key), ..} => 
+            Some(ref filter) =>
             {
-                let member = self.member_string(key)?;
-                self.members_member(member)?
+                let token = self.skip_token();
+                let filtered = !filter.keeps_subtree(&[]);
+                self.build(Start{token, skipped : true, filtered}, Some(filter))
             },
-            Token{token_type: TokenType::RightBrace, ..} => 
+            None =>
             {
-                let object = json::JsonObject::new();
-                return Ok(Object(object));
+                let token = self.next_token();
+                self.build(Start{token, skipped : false, filtered : false}, None)
             },
-            token => return Err(ParseError{token}),
         };
+        self.filter = filter;
+        let value = value?;
 
-        loop 
+        match self.next_token()
         {
-            match self.parse_object_members(members)?
-            {
-                Either::First(m) => members = m,
-                Either::Second(object) => return Ok(object),
-            }
+            Token{token_type:  TokenType::End, ..} => Ok(value),
+            token => Err(self.error(token)),
         }
     }
 
-    //member = String * ':' VALUE
-    fn member_string(&mut self, key : String) -> Result<'src,Member>
+    /// Parse a JSON Value and record where each value and key is written
+    ///
+    /// The spans are recorded while parsing, so the map covers exactly the
+    /// values that were built: members a filter drops are left out, a raw
+    /// value is a single entry and a repeated key maps to the member kept
+    pub fn parse_with_spans(&mut self) -> Result<'src, (json::JsonValue, SourceMap)>
+    {
+        self.spans = Some(SpanRecorder::new(self.source, self.lexer.options().duplicate_keys));
+        let value = self.parse();
+        let spans = self.spans.take();
+        Ok((value?, spans.map(SpanRecorder::finish).unwrap_or_default()))
+    }
+
+    /// The repeated keys of the last `parse` with all their values, in the
+    /// order the keys first repeat, if the policy is `DuplicateKeys::KeepAll`
+    pub fn duplicates(&self) -> &[DuplicateMember]
     {
-        match self.lexer.next()
+        &self.duplicates
+    }
+
+    /// Check that the source is a well-formed JSON Value without building it
+    ///
+    /// Accepts exactly the documents `parse` accepts and reports the same
+    /// errors, but never allocates strings, numbers or containers
+    pub fn validate(&mut self) -> Result<'src, ValidationStats>
+    {
+        self.reset();
+        self.check_input_size()?;
+        self.stats = ValidationStats::default();
+        let token = self.skip_token();
+        self.skip_value(token)?;
+
+        match self.skip_token()
         {
-            Token{token_type: TokenType::Colon, ..} => Ok(self.member_string_colon(key)?),
-            token => Err(ParseError{token}),
+            Token{token_type:  TokenType::End, ..} => Ok(self.stats),
+            token => Err(self.error(token)),
         }
     }
 
-    //member = String ':' * VALUE
+    /// Forget the state of an earlier call, which may have failed part way
+    fn reset(&mut self)
+    {
+        self.depth = 0;
+        self.pending = None;
+        self.allocated = 0;
+    }
+
+    /// Parse a single value starting with `token`,
+    /// leaving the lexer right after the value
+    pub(crate) fn parse_value(&mut self, token : Token<'src>) -> Result<'src, json::JsonValue>
+    {
+        self.build(Start{token, skipped : false, filtered : false}, None)
+    }
+
+    // * is current state of the parser
     //VALUE = * String
     //VALUE = * Number
     //VALUE = * Bool
     //VALUE = * Null
     //VALUE = * OBJECT
     //VALUE = * ARRAY
-    //OBJECT = '{' '}'
-    //OBJECT = '{' MEMBERS '}'
+    //OBJECT = * '{' '}'
+    //OBJECT = * '{' MEMBERS '}'
     //ARRAY = * '[' ']'
     //ARRAY = * '[' ELEMENTS ']'
-    fn member_string_colon(&mut self, key : String) -> Result<'src,Member>
+
+    /// Build the value beginning at `start`
+    fn build(&mut self, start : Start<'src>, filter : Option<&FieldFilter>) -> Result<'src, json::JsonValue>
     {
-        let value = match self.lexer.next()
+        let mut stack : Vec<Frame<'src>> = Vec::new();
+        let result = self.build_on(start, filter, &mut stack);
+        // after an error the containers being built may hold deep values
+        for frame in stack
         {
-            Token{token_type: TokenType::String(string),.. } =>
+            match frame
             {
-                self.reduce_value_string(string)?
-            },
-            Token{token_type: TokenType::Number(number),.. } =>
+                Frame::Object{object, ..} => json::drop_nested(json::JsonValue::JsonObject(object)),
+                Frame::Array{array, ..} => json::drop_nested(json::JsonValue::JsonArray(array)),
+            }
+        }
+        result
+    }
+
+    /// Build the value beginning at `start` on `stack`, which holds
+    /// the containers being built if this fails
+    fn build_on(&mut self, start : Start<'src>, filter : Option<&FieldFilter>,
+                stack : &mut Vec<Frame<'src>>) -> Result<'src, json::JsonValue>
+    {
+        let mut projection = Projection{filter, path : Vec::new()};
+        let Start{mut token, mut skipped, mut filtered} = start;
+
+        loop
+        {
+            // where the value being built starts, to report a limit it exceeds
+            let span = token.span;
+            if self.spans.is_some()
             {
-                self.reduce_value_number(number)?
-            },
-            Token{token_type: TokenType::Bool(b),.. } =>
+                let offset = self.offset(span);
+                let key = match stack.last()
+                {
+                    Some(Frame::Object{key, ..}) => Some((&*key.name, key.offset, key.offset + key.length)),
+                    _ => None,
+                };
+                if let Some(ref mut spans) = self.spans
+                {
+                    spans.start(offset, key);
+                }
+            }
+            let mut value = if self.captures(stack)
             {
-                self.reduce_value_bool(b)?
-            },
-            Token{token_type: TokenType::Null,.. } =>
+                self.capture_raw(token)?
+            }
+            else
             {
-                self.reduce_value_null()?
-            },
-            Token{token_type: TokenType::LeftBrace,.. } =>
+                match token
+                {
+                    Token{token_type: TokenType::String(string), span} =>
+                    {
+                        let string = if skipped {self.unescape(span)?.into_owned()} else {string};
+                        self.allocate(string.capacity(), span)?;
+                        json::JsonValue::JsonString(string)
+                    },
+                    Token{token_type: TokenType::Number(number), ..} => json::JsonValue::JsonNumber(number),
+                    Token{token_type: TokenType::Bool(b), ..} => json::JsonValue::JsonBool(b),
+                    Token{token_type: TokenType::Null, ..} => json::JsonValue::Null,
+                    Token{token_type: TokenType::LeftBrace, span} =>
+                    {
+                        self.enter(Token{span, token_type: TokenType::LeftBrace})?;
+                        let first = self.read(filtered);
+                        let mut key = MemberKey{name : Cow::Borrowed(""), offset : 0, length : 0};
+                        let mut members = Members::default();
+                        match self.object_member(first, true, &mut key, &mut members, filtered, &mut projection)?
+                        {
+                            Some(start) =>
+                            {
+                                stack.push(Frame::Object{object : json::JsonObject::new(), key, filtered, members});
+                                token = start.token;
+                                skipped = start.skipped;
+                                filtered = start.filtered;
+                                continue;
+                            },
+                            None =>
+                            {
+                                self.leave();
+                                json::JsonValue::JsonObject(json::JsonObject::new())
+                            },
+                        }
+                    },
+                    Token{token_type: TokenType::LeftBracket, span} =>
+                    {
+                        self.enter(Token{span, token_type: TokenType::LeftBracket})?;
+                        let first = self.read(filtered);
+                        if first.token_type == TokenType::RightBracket
+                        {
+                            self.leave();
+                            json::JsonValue::JsonArray(json::JsonArray::new())
+                        }
+                        else
+                        {
+                            let start = self.array_element(first, 0, filtered, &mut projection)?;
+                            stack.push(Frame::Array{array : json::JsonArray::new(), filtered});
+                            token = start.token;
+                            skipped = start.skipped;
+                            filtered = start.filtered;
+                            continue;
+                        }
+                    },
+                    token => return Err(self.error(token)),
+                }
+            };
+
+            // VALUE is complete, add it to the enclosing containers
+            // until one of them continues with another value
+            let start = loop
             {
-                let object = self.parse_object()?;
-                self.reduce_value_object(object)?
+                if let Some(ref mut spans) = self.spans
+                {
+                    spans.end(self.source.len() - self.lexer.remaining());
+                }
+                let next = match stack.split_last_mut()
+                {
+                    None => return Ok(value),
+
+                    // ELEMENTS = ELEMENTS ',' VALUE *
+                    Some((&mut Frame::Array{ref mut array, filtered : frame_filtered}, _)) =>
+                    {
+                        if frame_filtered
+                        {
+                            projection.path.pop();
+                        }
+                        let capacity = array.capacity();
+                        array.push(value);
+                        self.allocate((array.capacity() - capacity) * mem::size_of::<json::JsonValue>(), span)?;
+                        match self.read(frame_filtered)
+                        {
+                            Token{token_type: TokenType::Comma, ..} =>
+                            {
+                                let element = self.read(frame_filtered);
+                                if element.token_type == TokenType::RightBracket && self.lexer.options().trailing_commas
+                                {
+                                    None
+                                }
+                                else
+                                {
+                                    Some(self.array_element(element, array.len(), frame_filtered, &mut projection)?)
+                                }
+                            },
+                            Token{token_type: TokenType::RightBracket, ..} => None,
+                            token => return Err(self.error(token)),
+                        }
+                    },
+
+                    // member = String ':' VALUE *
+                    Some((&mut Frame::Object{ref mut object, ref mut key, ref mut members,
+                                             filtered : frame_filtered}, below)) =>
+                    {
+                        if frame_filtered
+                        {
+                            projection.path.pop();
+                        }
+                        let member_key = mem::replace(key, MemberKey{name : Cow::Borrowed(""), offset : 0, length : 0});
+                        self.insert_member(object, members, member_key, value, below)?;
+                        match self.read(frame_filtered)
+                        {
+                            Token{token_type: TokenType::Comma, ..} =>
+                            {
+                                let first = self.read(frame_filtered);
+                                let may_close = self.lexer.options().trailing_commas;
+                                self.object_member(first, may_close, key, members, frame_filtered, &mut projection)?
+                            },
+                            Token{token_type: TokenType::RightBrace, ..} => None,
+                            token => return Err(self.error(token)),
+                        }
+                    },
+                };
+
+                match next
+                {
+                    Some(start) => break start,
+                    None =>
+                    {
+                        // OBJECT = '{' MEMBERS '}' *
+                        // ARRAY = '[' ELEMENTS ']' *
+                        value = match stack.pop()
+                        {
+                            Some(Frame::Object{object, ..}) => json::JsonValue::JsonObject(object),
+                            Some(Frame::Array{array, ..}) => json::JsonValue::JsonArray(array),
+                            None => unreachable!(),
+                        };
+                        self.leave();
+                    },
+                }
+            };
+            token = start.token;
+            skipped = start.skipped;
+            filtered = start.filtered;
+        }
+    }
+
+    /// True if the value starting inside the containers of `stack`
+    /// is to be kept as raw text
+    fn captures(&self, stack : &[Frame<'src>]) -> bool
+    {
+        match self.capture
+        {
+            None => false,
+            Some(RawCapture::Fields(ref fields)) => match stack.last()
+            {
+                Some(Frame::Object{key, ..}) => fields.iter().any(|field| *field == key.name),
+                _ => false,
             },
-            Token{token_type: TokenType::LeftBracket,.. } =>
+            Some(RawCapture::Paths(ref patterns)) =>
             {
-                let array = self.parse_array()?;
-                self.reduce_value_array(array)?
+                // only build the path if a pattern is as deep as the value
+                let depth = stack.len();
+                patterns.iter().any(|p| p.len() == depth) &&
+                {
+                    let path = frames_path(stack);
+                    patterns.iter().any(|p| p.matches(&path))
+                }
             },
-            token => return Err(ParseError{token}),
-        };
-        self.member_string_colon_value(key,value) 
+        }
+    }
+
+    /// Check the value beginning with `token` and keep its text
+    fn capture_raw(&mut self, token : Token<'src>) -> Result<'src, json::JsonValue>
+    {
+        let start = self.offset(token.span);
+        let token_span = token.span;
+        self.skip_value(token)?;
+        let end = self.source.len() - self.lexer.remaining();
+        self.allocate(end - start, token_span)?;
+        Ok(json::JsonValue::JsonRaw(RawValue::captured(&self.source[start..end], self.lexer.options())))
     }
 
-    //member = String ':' VALUE * 
-    fn member_string_colon_value(&mut self, key : String, value : Value) -> Result<'src, Member>
+    /// An empty record of key offsets for a skipped object,
+    /// if duplicate keys are errors
+    fn skipped_keys(&self) -> SkippedKeys<'src>
     {
-        let Value(value) = value;
-        Ok(Member((key,value)))
+        match self.lexer.options().duplicate_keys
+        {
+            DuplicateKeys::Error => Some(HashMap::new()),
+            _ => None,
+        }
     }
 
-    // MEMBERS = member *
-    fn members_member(&mut self, member : Member) -> Result<'src, Members>
+    /// The error for the key at `second` repeating the one at `first`
+    fn duplicate_key(&self, first : usize, second : usize) -> ParseError<'src>
     {
-        let Member((key,value)) = member;
-        let mut object = json::JsonObject::new();
-        object.insert(key,value);
-        Ok(Members(object))
+        let token = self.lexer.token_at(&self.source[second..]);
+        self.limit_error(token, ErrorKind::DuplicateKey{first, second})
     }
 
-    // OBJECT = '{' MEMBERS * '}'
-    // MEMBERS =  MEMBERS * ',' member
-    fn parse_object_members(&mut self, members : Members) -> Result<'src, Either<Members, Object>>
+    /// Add a member to `object`, applying the duplicate key policy
+    ///
+    /// `below` are the containers enclosing `object`, which lead to its path
+    fn insert_member(&mut self, object : &mut json::JsonObject, members : &mut Members<'src>,
+                     key : MemberKey<'src>, value : json::JsonValue, below : &[Frame<'src>]) -> Result<'src, ()>
     {
-        match self.lexer.next()
+        let name = key.name.into_owned();
+        // a new key is stored twice, in the entries and in the index
+        let key_bytes = name.capacity() + name.len();
+        let (length, allocated) = (object.len(), object.allocated());
+
+        let policy = self.lexer.options().duplicate_keys;
+        match (policy, object.position(&name))
         {
-            Token{token_type: TokenType::Comma, ..} =>
+            (DuplicateKeys::Error, None) | (DuplicateKeys::KeepAll, None) =>
+            {
+                members.first.push((key.offset, None));
+                object.insert(name, value);
+            },
+            (DuplicateKeys::Error, Some(position)) =>
+            {
+                json::drop_nested(value);
+                return Err(self.duplicate_key(members.first[position].0, key.offset));
+            },
+            (DuplicateKeys::KeepAll, Some(position)) =>
             {
-                let members = self.members_members_comma(members)?;
-                Ok(Either::First(members))
+                let (first, record) = members.first[position];
+                let record = match record
+                {
+                    Some(record) => record,
+                    None =>
+                    {
+                        let mut path = frames_path(below);
+                        path.push(&name);
+                        self.duplicates.push(DuplicateMember{path, values : Vec::new()});
+                        members.first[position].1 = Some(self.duplicates.len() - 1);
+                        self.duplicates.len() - 1
+                    },
+                };
+                let copy = value.clone();
+                if let Some(earlier) = object.insert(name, value)
+                {
+                    // the first value is only in the object, later ones already have a copy
+                    let values = &mut self.duplicates[record].values;
+                    if values.is_empty()
+                    {
+                        values.push((first, earlier));
+                    }
+                    else
+                    {
+                        json::drop_nested(earlier);
+                    }
+                    values.push((key.offset, copy));
+                }
             },
-            Token{token_type: TokenType::RightBrace, ..} => 
+            (DuplicateKeys::FirstWins, Some(_)) => {},
+            _ =>
             {
-                let Members(object) = members;
-                Ok(Either::Second(Object(object)))
+                object.insert(name, value);
             },
-            token => return Err(ParseError{token}),
         }
+
+        let mut bytes = object.allocated() - allocated;
+        if object.len() > length
+        {
+            bytes += key_bytes;
+        }
+        self.allocate(bytes, &self.source[key.offset..])
     }
 
-    // MEMBERS =  MEMBERS ',' * member
-    // member = * String : Value
-    fn members_members_comma(&mut self, members : Members) -> Result<'src, Members>
+    /// Byte offset of `span` in the source
+    fn offset(&self, span : &'src str) -> usize
     {
-        let member = match self.lexer.next()
+        span.as_ptr() as usize - self.source.as_ptr() as usize
+    }
+
+    /// Record the key at `offset` of a member the filter drops, failing
+    /// if an earlier member had it and the policy is `DuplicateKeys::Error`
+    ///
+    /// The filter keeps or drops every member with the same key alike,
+    /// so dropped keys only need checking against each other
+    fn see_dropped_key(&self, members : &mut Members<'src>, name : Cow<'src, str>, offset : usize) -> Result<'src, ()>
+    {
+        if self.lexer.options().duplicate_keys != DuplicateKeys::Error
+        {
+            return Ok(());
+        }
+        match members.dropped.insert(name, offset)
         {
-            Token{token_type: TokenType::String(key), ..} => self.member_string(key)?,
-            token => return Err(ParseError{token}),
+            Some(first) => Err(self.duplicate_key(first, offset)),
+            None => Ok(()),
+        }
+    }
+
+    /// Record the key `token` of a member that is skipped, failing if the
+    /// object already has it and the policy is `DuplicateKeys::Error`
+    fn see_key(&self, seen : &mut SkippedKeys<'src>, token : &Token<'src>) -> Result<'src, ()>
+    {
+        let seen = match *seen
+        {
+            Some(ref mut seen) => seen,
+            None => return Ok(()),
         };
-        Ok(self.members_members_comma_member(members, member)?)
+        if !self.is_key(token)
+        {
+            return Ok(());
+        }
+        let second = self.offset(token.span);
+        match seen.insert(self.unescape(token.span)?, second)
+        {
+            Some(first) => Err(self.limit_error(token.clone(), ErrorKind::DuplicateKey{first, second})),
+            None => Ok(()),
+        }
     }
 
-    // MEMBERS =  MEMBERS ',' member * -> MEMBERS
-    fn members_members_comma_member(&mut self, members : Members, member : Member) -> Result<'src, Members>
+    /// Read the next token, without decoding strings if `skipped`
+    fn read(&mut self, skipped : bool) -> Token<'src>
     {
-        let Members(mut object) = members;
-        let Member((key,value)) = member;
-        object.insert(key,value);
-        Ok(Members(object))
+        if skipped {self.skip_token()} else {self.next_token()}
     }
 
-    //ARRAY = * '[' ']'
-    //ARRAY = * '[' ELEMENTS ']'
-    fn parse_array(&mut self) -> Result<'src,Array>
+    //OBJECT = '{' * MEMBERS '}'
+    //OBJECT = '{' * '}'
+    //MEMBERS = MEMBERS ',' * member
+    //member = * String ':' VALUE
+    /// Read members beginning at `token` until one that is kept, storing
+    /// its key, and return the start of its value, or None at '}'
+    ///
+    /// '}' is accepted in place of the first member if `may_close`
+    fn object_member(&mut self, mut token : Token<'src>, mut may_close : bool,
+                     key : &mut MemberKey<'src>, members : &mut Members<'src>, filtered : bool,
+                     projection : &mut Projection<'_, 'src>) -> Result<'src, Option<Start<'src>>>
     {
-        let value = match self.lexer.next()
+        loop
         {
-            Token{token_type: TokenType::String(string),..} =>
+            if may_close && token.token_type == TokenType::RightBrace
             {
-                self.reduce_value_string(string)?
+                return Ok(None);
             }
-            Token{token_type: TokenType::Number(number),..} => 
+            if !self.is_key(&token)
             {
-                self.reduce_value_number(number)?
-            },
-            Token{token_type: TokenType::Bool(b),..} => 
+                return Err(self.error(token));
+            }
+            self.check_members(members.count, token.span)?;
+            members.count += 1;
+            let (offset, length) = (self.offset(token.span), token.span.len());
+            let member_key = match token
             {
-                self.reduce_value_bool(b)?
-            },
-            Token{token_type: TokenType::Null,..} =>
+                Token{token_type: TokenType::String(string), ..} if !filtered => Cow::Owned(string),
+                Token{span, ..} => self.unescape(span)?,
+            };
+
+            // member = String * ':' VALUE
+            match self.read(filtered)
             {
-                self.reduce_value_null()?
-            },
-            Token{token_type: TokenType::RightBracket, ..} => 
+                Token{token_type: TokenType::Colon, ..} => {},
+                token => return Err(self.error(token)),
+            }
+
+            // member = String ':' * VALUE
+            let token_value = self.read(filtered);
+            let filter = match projection.filter
             {
-                let array = json::JsonArray::new();
-                return Ok(Array(array));
-            },
-            Token{token_type: TokenType::LeftBracket, ..} =>
+                Some(filter) if filtered => filter,
+                _ =>
+                {
+                    *key = MemberKey{name : member_key, offset, length};
+                    return Ok(Some(Start{token : token_value, skipped : filtered, filtered : false}));
+                },
+            };
+
+            projection.path.push(Step::Key(member_key.clone()));
+            if filter.keeps_member(&projection.path)
             {
-                let array = self.parse_array()?;
-                self.reduce_value_array(array)?
-            },
-            Token{token_type: TokenType::LeftBrace, ..} => 
+                *key = MemberKey{name : member_key, offset, length};
+                let filtered = !filter.keeps_subtree(&projection.path);
+                return Ok(Some(Start{token : token_value, skipped : true, filtered}));
+            }
+            projection.path.pop();
+            self.see_dropped_key(members, member_key, offset)?;
+            self.skip_value(token_value)?;
+
+            // MEMBERS = MEMBERS * ',' member
+            // OBJECT = '{' MEMBERS * '}'
+            match self.skip_token()
             {
-                let object = self.parse_object()?;
-                self.reduce_value_object(object)?
-            },
-            token => return Err(ParseError{token}),
-        };
+                Token{token_type: TokenType::Comma, ..} =>
+                {
+                    token = self.skip_token();
+                    may_close = self.lexer.options().trailing_commas;
+                },
+                Token{token_type: TokenType::RightBrace, ..} => return Ok(None),
+                token => return Err(self.error(token)),
+            }
+        }
+    }
 
-        let mut elements = self.elements_value(value)?;
-        loop
+    //ELEMENTS = * VALUE
+    //ELEMENTS = ELEMENTS ',' * VALUE
+    /// Start the element at `index` beginning with `token`
+    fn array_element(&mut self, token : Token<'src>, index : usize, filtered : bool,
+                     projection : &mut Projection<'_, 'src>) -> Result<'src, Start<'src>>
+    {
+        self.check_elements(index, token.span)?;
+        match projection.filter
         {
-            match self.parse_array_elements(elements)?
+            Some(filter) if filtered =>
             {
-                Either::First(e) => elements = e,
-                Either::Second(array) => return Ok(array),
-            }
+                projection.path.push(Step::Index(index));
+                let filtered = !filter.keeps_subtree(&projection.path);
+                Ok(Start{token, skipped : true, filtered})
+            },
+            _ => Ok(Start{token, skipped : filtered, filtered : false}),
         }
     }
 
-    //MEMBERS = VALUE
-    fn elements_value(&mut self, value : Value) -> Result<'src, Elements>
+    /// Get the next token, decoding strings
+    fn next_token(&mut self) -> Token<'src>
+    {
+        let token = self.lexer.next();
+        self.check_token(token)
+    }
+
+    /// Get the next token without decoding strings
+    pub(crate) fn skip_token(&mut self) -> Token<'src>
     {
-        let Value(member) = value;
-        let mut array = json::JsonArray::new();
-        array.push(member);
-        Ok(Elements(array))
+        let token = self.lexer.skip();
+        self.check_token(token)
     }
 
-    //array = '[' elements *']'
-    //elements = elements * , value
-    fn parse_array_elements(&mut self, elements : Elements) ->Result<'src,Either<Elements,Array>>
+    /// Apply the limits that concern a single token
+    ///
+    /// A token exceeding a limit is turned into an error token,
+    /// which fails the parse wherever it appears
+    fn check_token(&mut self, token : Token<'src>) -> Token<'src>
     {
-        match self.lexer.next()
+        let limits = match self.limits
         {
-            Token{token_type: TokenType::Comma, ..} =>
+            Some(ref limits) => limits,
+            None => return token,
+        };
+
+        self.tokens += 1;
+        let kind = if self.tokens % CANCEL_INTERVAL == 0 && limits.is_cancelled()
+        {
+            Some(ErrorKind::Cancelled)
+        }
+        else
+        {
+            // the lexer stops reading a string at the limit
+            match (&token.token_type, limits.max_string_length)
             {
-                let elements = self.elements_elements_comma(elements)?;
-                Ok(Either::First(elements))
-            },
-            Token{token_type: TokenType::RightBracket,..} => 
+                (&TokenType::LongString, Some(max)) => Some(ErrorKind::StringLengthLimitExceeded(max)),
+                _ => None,
+            }
+        };
+
+        match kind
+        {
+            Some(kind) =>
             {
-                let Elements(array) = elements;
-                Ok(Either::Second(Array(array)))
+                self.pending = Some(kind);
+                Token{span : token.span, token_type : TokenType::Error}
             },
-            token => Err(ParseError{token})
+            None => token,
         }
     }
 
-    // ELEMENTS = ELEMENTS , * VALUE
-    // VALUE = * STRING
-    // VALUE = * NUMBER
-    // VALUE = * BOOL
-    // VALUE = * NULL
-    // VALUE = * OBJECT
-    // VALUE = * ARRAY
-    // OBJECT = * '{' '}' 
-    // OBJECT = * '{' MEMBERS '}' 
-    // ARRAY = * '[' ']'
-    // ARRAY = * '[' ELEMENTS ']'
-    fn elements_elements_comma(&mut self, elements : Elements) -> Result<'src,Elements>
+    /// Build the error for an unexpected `token`,
+    /// or for the limit it exceeded
+    pub(crate) fn error(&mut self, token : Token<'src>) -> ParseError<'src>
+    {
+        let kind = self.pending.take().unwrap_or(ErrorKind::UnexpectedToken);
+        ParseError{token : self.lexer.decode(token), kind}
+    }
+
+    /// Count `bytes` more allocated for the document, failing at the
+    /// token beginning `span` if that exceeds `max_allocated_bytes`
+    fn allocate(&mut self, bytes : usize, span : &'src str) -> Result<'src, ()>
     {
-        let value = match self.lexer.next()
+        let max = match self.limits
         {
-            Token{token_type: TokenType::String(string),..} => 
-            {
-                self.reduce_value_string(string)?
-            },
-            Token{token_type: TokenType::Number(number) ,..} =>
+            Some(ParserLimits{max_allocated_bytes : Some(max), ..}) => max,
+            _ => return Ok(()),
+        };
+        self.allocated += bytes;
+        if self.allocated > max
+        {
+            let token = self.lexer.token_at(span);
+            return Err(self.limit_error(token, ErrorKind::AllocationLimitExceeded(max)));
+        }
+        Ok(())
+    }
+
+    fn limit_error(&self, token : Token<'src>, kind : ErrorKind) -> ParseError<'src>
+    {
+        ParseError{token : self.lexer.decode(token), kind}
+    }
+
+    /// Decodes a token read by `skip_token`
+    pub(crate) fn decode(&self, token : Token<'src>) -> Token<'src>
+    {
+        self.lexer.decode(token)
+    }
+
+    /// Decodes the key or string at `span`, failing if it is malformed
+    pub(crate) fn unescape(&self, span : &'src str) -> Result<'src, Cow<'src, str>>
+    {
+        match self.lexer.unescape(span)
+        {
+            Some(decoded) => Ok(decoded),
+            None => Err(self.limit_error(Token{span, token_type : TokenType::Error}, ErrorKind::UnexpectedToken)),
+        }
+    }
+
+    /// True if `token` can be the key of a member
+    ///
+    /// Unquoted keys may also be spelled like a literal, such as `null` or `NaN`
+    fn is_key(&self, token : &Token<'src>) -> bool
+    {
+        match token.token_type
+        {
+            TokenType::String(_) => true,
+            TokenType::Identifier(_) | TokenType::Bool(_) | TokenType::Null => self.lexer.options().unquoted_keys,
+            TokenType::Number(_) => self.lexer.options().unquoted_keys && token.span.starts_with(char::is_alphabetic),
+            _ => false,
+        }
+    }
+
+    fn check_input_size(&mut self) -> Result<'src, ()>
+    {
+        let max = match self.limits
+        {
+            Some(ParserLimits{max_input_size : Some(max), ..}) => max,
+            _ => return Ok(()),
+        };
+        if self.lexer.remaining() > max
+        {
+            let token = self.lexer.skip();
+            return Err(self.limit_error(token, ErrorKind::InputSizeLimitExceeded(max)));
+        }
+        Ok(())
+    }
+
+    /// Enter an object or array opened by `token`
+    pub(crate) fn enter(&mut self, token : Token<'src>) -> Result<'src, ()>
+    {
+        self.depth += 1;
+        if let Some(ref limits) = self.limits
+        {
+            if let Some(max) = limits.max_depth
             {
-                self.reduce_value_number(number)?
-            },
-            Token{token_type: TokenType::Bool(b) ,..} =>
+                if self.depth > max
+                {
+                    return Err(self.limit_error(token, ErrorKind::DepthLimitExceeded(max)));
+                }
+            }
+            if limits.is_cancelled()
             {
-                self.reduce_value_bool(b)?
-            },
-            Token{token_type: TokenType::Null ,..} =>
+                return Err(self.limit_error(token, ErrorKind::Cancelled));
+            }
+        }
+        Ok(())
+    }
+
+    /// Leave the innermost object or array
+    pub(crate) fn leave(&mut self)
+    {
+        self.depth -= 1;
+    }
+
+    /// Check that an object with `count` members may take the one with the key at `span`
+    pub(crate) fn check_members(&self, count : usize, span : &'src str) -> Result<'src, ()>
+    {
+        match self.limits
+        {
+            Some(ParserLimits{max_object_members : Some(max), ..}) if count >= max =>
             {
-                self.reduce_value_null()?
+                Err(self.limit_error(self.lexer.token_at(span), ErrorKind::MemberCountLimitExceeded(max)))
             },
-            Token{token_type: TokenType::LeftBrace ,..} =>
+            _ => Ok(()),
+        }
+    }
+
+    /// Check that an array with `count` elements may take the one starting at `span`
+    pub(crate) fn check_elements(&self, count : usize, span : &'src str) -> Result<'src, ()>
+    {
+        match self.limits
+        {
+            Some(ParserLimits{max_array_length : Some(max), ..}) if count >= max =>
             {
-                let object = self.parse_object()?;
-                self.reduce_value_object(object)?
+                Err(self.limit_error(self.lexer.token_at(span), ErrorKind::ArrayLengthLimitExceeded(max)))
             },
-            Token{token_type: TokenType::LeftBracket ,..} =>
+            _ => Ok(()),
+        }
+    }
+
+    /// Check the syntax of a value starting with `token` without building it
+    pub(crate) fn skip_value(&mut self, mut token : Token<'src>) -> Result<'src, ()>
+    {
+        // whether each open container is an object, its number of members or elements,
+        // and for an object the keys seen if duplicates are errors
+        let mut stack : Vec<(bool, usize, SkippedKeys<'src>)> = Vec::new();
+        loop
+        {
+            self.stats.values += 1;
+            match token.token_type
             {
-               let array = self.parse_array()?;
-               self.reduce_value_array(array)?
-            },
-            token => return Err(ParseError{token}),
-        };
+                TokenType::String(_) | TokenType::Number(_) | TokenType::Bool(_) | TokenType::Null => {},
+                TokenType::LeftBrace | TokenType::LeftBracket =>
+                {
+                    let is_object = token.token_type == TokenType::LeftBrace;
+                    self.enter(token)?;
+                    if self.depth > self.stats.max_depth
+                    {
+                        self.stats.max_depth = self.depth;
+                    }
 
-        Ok(self.elements_elements_comma_value(elements,value)?)
+                    let first = self.skip_token();
+                    match first.token_type
+                    {
+                        TokenType::RightBrace if is_object => self.leave(),
+                        TokenType::RightBracket if !is_object => self.leave(),
+                        _ if is_object =>
+                        {
+                            self.check_members(0, first.span)?;
+                            let mut seen = self.skipped_keys();
+                            self.see_key(&mut seen, &first)?;
+                            token = self.skip_member(first)?;
+                            stack.push((true, 1, seen));
+                            continue;
+                        },
+                        _ =>
+                        {
+                            self.check_elements(0, first.span)?;
+                            token = first;
+                            stack.push((false, 1, None));
+                            continue;
+                        },
+                    }
+                },
+                _ => return Err(self.error(token)),
+            }
 
+            // VALUE is complete, close containers until one continues
+            loop
+            {
+                let (is_object, count) = match stack.last()
+                {
+                    Some(&(is_object, count, _)) => (is_object, count),
+                    None => return Ok(()),
+                };
+                match self.skip_token()
+                {
+                    Token{token_type: TokenType::Comma, ..} =>
+                    {
+                        let next = self.skip_token();
+                        let closes = match next.token_type
+                        {
+                            TokenType::RightBrace => is_object,
+                            TokenType::RightBracket => !is_object,
+                            _ => false,
+                        };
+                        if closes && self.lexer.options().trailing_commas
+                        {
+                            stack.pop();
+                            self.leave();
+                            continue;
+                        }
+                        if is_object
+                        {
+                            self.check_members(count, next.span)?;
+                            if let Some(&mut (_, _, ref mut seen)) = stack.last_mut()
+                            {
+                                self.see_key(seen, &next)?;
+                            }
+                            token = self.skip_member(next)?;
+                        }
+                        else
+                        {
+                            self.check_elements(count, next.span)?;
+                            token = next;
+                        }
+                        if let Some(top) = stack.last_mut()
+                        {
+                            top.1 += 1;
+                        }
+                        break;
+                    },
+                    Token{token_type: TokenType::RightBrace, ..} if is_object =>
+                    {
+                        stack.pop();
+                        self.leave();
+                    },
+                    Token{token_type: TokenType::RightBracket, ..} if !is_object =>
+                    {
+                        stack.pop();
+                        self.leave();
+                    },
+                    token => return Err(self.error(token)),
+                }
+            }
+        }
     }
 
-    // ELEMENTS = ELEMENTS , VALUE *
-    fn elements_elements_comma_value(&mut self, elements : Elements, value : Value) -> Result<'src, Elements>
+    //member = * String ':' VALUE
+    /// Skip the key and colon of a member, returning the first token of its value
+    fn skip_member(&mut self, key : Token<'src>) -> Result<'src, Token<'src>>
     {
-        let Elements(mut array) = elements;
-        let Value(value) = value;
-        array.push(value);
-        Ok(Elements(array))
+        if !self.is_key(&key)
+        {
+            return Err(self.error(key));
+        }
+        match self.skip_token()
+        {
+            Token{token_type: TokenType::Colon, ..} => Ok(self.skip_token()),
+            token => Err(self.error(token)),
+        }
     }
-
-
-}
\ No newline at end of file
+}
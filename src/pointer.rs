@@ -0,0 +1,268 @@
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+
+/// A JSON Pointer (RFC 6901)
+///
+/// Identifies a single value inside a JSON document by the
+/// sequence of object keys and array indices leading to it
+#[derive(Debug,Clone,PartialEq,Eq,Hash,Default)]
+pub struct JsonPointer
+{
+    segments : Vec<String>,
+}
+
+/// A path pattern - a JSON Pointer in which a segment `*`
+/// matches any object key or array index
+#[derive(Debug,Clone,PartialEq)]
+pub struct PathPattern
+{
+    segments : Vec<PatternSegment>,
+}
+
+#[derive(Debug,Clone,PartialEq)]
+enum PatternSegment
+{
+    Token(String, Option<usize>),
+    Wildcard,
+}
+
+/// A single step on the way from the root to a value,
+/// used while walking a document without building it
+#[derive(Debug,Clone,PartialEq)]
+pub(crate) enum Step<'a>
+{
+    Key(Cow<'a, str>),
+    Index(usize),
+}
+
+#[derive(Debug,PartialEq)]
+pub struct InvalidPointerError
+{
+    pub(crate) pointer : String,
+}
+
+impl fmt::Display for InvalidPointerError
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid JSON pointer: {:?}", self.pointer)
+    }
+}
+
+impl Error for InvalidPointerError
+{
+    fn description(&self) -> &str {
+        "Invalid JSON pointer"
+    }
+}
+
+/// Splits a pointer into its unescaped reference tokens
+fn split(pointer : &str) -> Result<Vec<String>, InvalidPointerError>
+{
+    if pointer.is_empty()
+    {
+        return Ok(Vec::new());
+    }
+    if !pointer.starts_with('/')
+    {
+        return Err(InvalidPointerError{pointer : pointer.to_owned()});
+    }
+
+    let mut segments = Vec::new();
+    for raw in pointer[1..].split('/')
+    {
+        let mut segment = String::with_capacity(raw.len());
+        let mut chars = raw.chars();
+        while let Some(c) = chars.next()
+        {
+            match c
+            {
+                '~' => match chars.next()
+                {
+                    Some('0') => segment.push('~'),
+                    Some('1') => segment.push('/'),
+                    _ => return Err(InvalidPointerError{pointer : pointer.to_owned()}),
+                },
+                c => segment.push(c),
+            }
+        }
+        segments.push(segment);
+    }
+    Ok(segments)
+}
+
+/// Parses an array index reference token, without leading zeros
+fn parse_index(segment : &str) -> Option<usize>
+{
+    match segment.as_bytes()
+    {
+        [b'0'] => Some(0),
+        [b'1'..=b'9', ..] => segment.parse().ok(),
+        _ => None,
+    }
+}
+
+fn write_escaped(f : &mut fmt::Formatter, segment : &str) -> fmt::Result
+{
+    for c in segment.chars()
+    {
+        match c
+        {
+            '~' => f.write_str("~0")?,
+            '/' => f.write_str("~1")?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    Ok(())
+}
+
+impl JsonPointer
+{
+    /// The pointer to the whole document
+    pub fn root() -> JsonPointer
+    {
+        JsonPointer{segments : Vec::new()}
+    }
+
+    /// Parse a pointer in its string form, e.g. `/items/0/id`
+    pub fn parse(pointer : &str) -> Result<JsonPointer, InvalidPointerError>
+    {
+        Ok(JsonPointer{segments : split(pointer)?})
+    }
+
+    pub fn segments(&self) -> &[String]
+    {
+        &self.segments
+    }
+
+    pub fn is_root(&self) -> bool
+    {
+        self.segments.is_empty()
+    }
+
+    pub fn push(&mut self, segment : &str)
+    {
+        self.segments.push(segment.to_owned());
+    }
+
+    pub fn pop(&mut self) -> Option<String>
+    {
+        self.segments.pop()
+    }
+
+    pub(crate) fn from_steps(steps : &[Step]) -> JsonPointer
+    {
+        let segments = steps.iter().map(|step| match *step
+        {
+            Step::Key(ref key) => key.to_string(),
+            Step::Index(index) => index.to_string(),
+        }).collect();
+        JsonPointer{segments}
+    }
+}
+
+impl fmt::Display for JsonPointer
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for segment in &self.segments
+        {
+            f.write_str("/")?;
+            write_escaped(f, segment)?;
+        }
+        Ok(())
+    }
+}
+
+impl PathPattern
+{
+    /// Parse a pattern in JSON Pointer form, e.g. `/items/*/id`
+    pub fn parse(pattern : &str) -> Result<PathPattern, InvalidPointerError>
+    {
+        let segments = split(pattern)?.into_iter().map(|segment|
+        {
+            if segment == "*"
+            {
+                PatternSegment::Wildcard
+            }
+            else
+            {
+                let index = parse_index(&segment);
+                PatternSegment::Token(segment, index)
+            }
+        }).collect();
+        Ok(PathPattern{segments})
+    }
+
+    /// True if the pattern contains no wildcards and so matches at most one value
+    pub fn is_exact(&self) -> bool
+    {
+        self.segments.iter().all(|s| *s != PatternSegment::Wildcard)
+    }
+
+    /// Number of segments, the depth of the values the pattern matches
+    pub(crate) fn len(&self) -> usize
+    {
+        self.segments.len()
+    }
+
+    pub fn matches(&self, pointer : &JsonPointer) -> bool
+    {
+        self.segments.len() == pointer.segments.len() &&
+        self.segments.iter().zip(&pointer.segments).all(|(p, s)| p.matches_key(s))
+    }
+
+    /// True if the pattern matches the value at the end of `steps`
+    pub(crate) fn matches_steps(&self, steps : &[Step]) -> bool
+    {
+        self.segments.len() == steps.len() && self.prefix_matches(steps)
+    }
+
+    /// True if the pattern may match a value nested below the end of `steps`
+    pub(crate) fn matches_below(&self, steps : &[Step]) -> bool
+    {
+        self.segments.len() > steps.len() && self.prefix_matches(steps)
+    }
+
+    fn prefix_matches(&self, steps : &[Step]) -> bool
+    {
+        self.segments.iter().zip(steps).all(|(p, s)| p.matches_step(s))
+    }
+}
+
+impl PatternSegment
+{
+    fn matches_key(&self, key : &str) -> bool
+    {
+        match *self
+        {
+            PatternSegment::Wildcard => true,
+            PatternSegment::Token(ref token, _) => token == key,
+        }
+    }
+
+    fn matches_step(&self, step : &Step) -> bool
+    {
+        match (self, step)
+        {
+            (PatternSegment::Wildcard, _) => true,
+            (PatternSegment::Token(ref token, _), Step::Key(key)) => *token == **key,
+            (PatternSegment::Token(_, index), Step::Index(i)) => *index == Some(*i),
+        }
+    }
+}
+
+impl fmt::Display for PathPattern
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for segment in &self.segments
+        {
+            f.write_str("/")?;
+            match *segment
+            {
+                PatternSegment::Wildcard => f.write_str("*")?,
+                PatternSegment::Token(ref token, _) => write_escaped(f, token)?,
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+use json::JsonValue;
+use options::{DuplicateKeys,ParserOptions};
+use parse_error::Result;
+use parser::JsonParser;
+use pointer::PathPattern;
+use std::fmt;
+
+/// A value kept as its exact source text, unparsed
+///
+/// A raw value read as strict JSON is written back byte for byte, so
+/// numbers and escapes keep their spelling, unless the output asks for
+/// more escapes or pretty objects and arrays; then, like text read with
+/// extensions, it is parsed and written as any other value. The text is
+/// known to be a single value; `parse` turns it into a `JsonValue` when
+/// it is needed.
+#[derive(Debug,Clone,PartialEq)]
+pub struct RawValue
+{
+    text : String,
+    options : ParserOptions,
+}
+
+/// Which values the parser keeps as raw text instead of building them
+#[derive(Debug,Clone)]
+pub enum RawCapture
+{
+    /// Values at paths matching a pattern
+    Paths(Vec<PathPattern>),
+    /// Values of members with one of these keys, at any depth
+    Fields(Vec<String>),
+}
+
+impl RawValue
+{
+    /// A raw value of the strict JSON `text`, without surrounding whitespace
+    pub fn new<'src>(text : &'src str) -> Result<'src, RawValue>
+    {
+        JsonParser::new(text).validate()?;
+        Ok(RawValue{text : text.trim().to_owned(), options : ParserOptions::new()})
+    }
+
+    /// A value captured by a parser that accepts the syntax of `options`
+    pub(crate) fn captured(text : &str, options : ParserOptions) -> RawValue
+    {
+        RawValue{text : text.to_owned(), options}
+    }
+
+    /// True if the text was read as strict JSON, whatever the duplicate key policy
+    pub(crate) fn is_strict(&self) -> bool
+    {
+        self.options.duplicate_keys(DuplicateKeys::default()) == ParserOptions::new()
+    }
+
+    pub fn as_str(&self) -> &str
+    {
+        &self.text
+    }
+
+    pub fn into_string(self) -> String
+    {
+        self.text
+    }
+
+    /// Parse the text, with the syntax of the document it came from
+    pub fn parse(&self) -> Result<JsonValue>
+    {
+        JsonParser::new(&self.text).with_options(self.options).parse()
+    }
+}
+
+impl fmt::Display for RawValue
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
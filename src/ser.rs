@@ -0,0 +1,824 @@
+use json::{self,JsonArray,JsonObject,JsonValue};
+use map;
+use raw::RawValue;
+use std::error::Error;
+use std::fmt::{self, Write};
+use std::{io, slice, vec};
+
+/// Why a value could not be written
+#[derive(Debug)]
+pub enum SerializeError
+{
+    /// NaN or an infinity, which JSON cannot represent
+    NonFiniteNumber(f64),
+    /// The `fmt::Write` being written to failed
+    Fmt(fmt::Error),
+    /// The `io::Write` being written to failed
+    Io(io::Error),
+    /// A `JsonWriter` call that would not give well-formed JSON
+    InvalidStructure(&'static str),
+    /// A raw fragment that is not a single JSON value
+    InvalidFragment(String),
+}
+
+impl fmt::Display for SerializeError
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self
+        {
+            SerializeError::NonFiniteNumber(number) => write!(f, "Cannot write non-finite number {} as JSON", number),
+            SerializeError::Fmt(_) => write!(f, "Formatter error while writing JSON"),
+            SerializeError::Io(ref error) => write!(f, "I/O error while writing JSON: {}", error),
+            SerializeError::InvalidStructure(message) => write!(f, "Invalid JSON structure: {}", message),
+            SerializeError::InvalidFragment(ref message) => write!(f, "Invalid JSON fragment: {}", message),
+        }
+    }
+}
+
+impl Error for SerializeError
+{
+    fn description(&self) -> &str {
+        "Cannot write JSON"
+    }
+}
+
+impl From<fmt::Error> for SerializeError
+{
+    fn from(error : fmt::Error) -> SerializeError
+    {
+        SerializeError::Fmt(error)
+    }
+}
+
+/// How indentation is written in pretty output
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Indent
+{
+    /// This many spaces per level
+    Spaces(usize),
+    /// One tab per level
+    Tab,
+}
+
+/// The line ending of pretty output
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum LineEnding
+{
+    Lf,
+    CrLf,
+}
+
+/// Layout of pretty-printed JSON
+///
+/// The same value and configuration always give the same text. By
+/// default members and elements go on their own lines, indented by two
+/// spaces, with a space after each colon and `\n` line endings.
+#[derive(Debug,Clone,PartialEq)]
+pub struct PrettyConfig
+{
+    pub(crate) indent : Indent,
+    pub(crate) sort_keys : bool,
+    pub(crate) space_after_colon : bool,
+    pub(crate) line_ending : LineEnding,
+    pub(crate) max_width : Option<usize>,
+    pub(crate) final_newline : bool,
+}
+
+/// How finite numbers are written
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum NumberFormat
+{
+    /// The fewest digits that read back as the same `f64`, in plain
+    /// notation from 1e-6 up to 1e21 and with an exponent outside it,
+    /// as JavaScript does: `1.0` is written `1`
+    Shortest,
+    /// Plain notation with this many digits after the decimal point
+    Fixed(usize),
+    /// The fewest digits with an exponent, such as `1.5e3`
+    Exponent,
+}
+
+/// What is written for NaN and the infinities, which JSON lacks
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum NonFinite
+{
+    /// Fail with `SerializeError::NonFiniteNumber`
+    Error,
+    /// Write `null`
+    Null,
+    /// Write the string `"NaN"`, `"Infinity"` or `"-Infinity"`
+    String,
+}
+
+impl Default for NumberFormat
+{
+    fn default() -> NumberFormat
+    {
+        NumberFormat::Shortest
+    }
+}
+
+impl Default for NonFinite
+{
+    fn default() -> NonFinite
+    {
+        NonFinite::Error
+    }
+}
+
+/// Which characters of strings and keys are written as escapes
+///
+/// The default, `Escaping::minimal()`, escapes only what JSON requires
+/// and so gives the shortest output. Each policy adds escapes that keep
+/// the text readable by the same parsers; they can be combined.
+#[derive(Debug,Default,Clone,Copy,PartialEq)]
+pub struct Escaping
+{
+    pub(crate) ascii : bool,
+    pub(crate) html : bool,
+    pub(crate) line_separators : bool,
+}
+
+/// How values are written
+///
+/// The default is compact RFC 8259 JSON, without any whitespace
+#[derive(Debug,Default,Clone,PartialEq)]
+pub struct SerializerOptions
+{
+    pub(crate) pretty : Option<PrettyConfig>,
+    pub(crate) escaping : Escaping,
+    pub(crate) numbers : NumberFormat,
+    pub(crate) non_finite : NonFinite,
+}
+
+impl PrettyConfig
+{
+    pub fn new() -> PrettyConfig
+    {
+        PrettyConfig
+        {
+            indent : Indent::Spaces(2),
+            sort_keys : false,
+            space_after_colon : true,
+            line_ending : LineEnding::Lf,
+            max_width : None,
+            final_newline : false,
+        }
+    }
+
+    /// Indent each level by `width` spaces
+    pub fn indent_width(mut self, width : usize) -> PrettyConfig
+    {
+        self.indent = Indent::Spaces(width);
+        self
+    }
+
+    /// Indent each level by a tab
+    pub fn tabs(mut self) -> PrettyConfig
+    {
+        self.indent = Indent::Tab;
+        self
+    }
+
+    /// Write object members ordered by key rather than in insertion order
+    pub fn sort_keys(mut self, sort : bool) -> PrettyConfig
+    {
+        self.sort_keys = sort;
+        self
+    }
+
+    pub fn space_after_colon(mut self, space : bool) -> PrettyConfig
+    {
+        self.space_after_colon = space;
+        self
+    }
+
+    pub fn line_ending(mut self, line_ending : LineEnding) -> PrettyConfig
+    {
+        self.line_ending = line_ending;
+        self
+    }
+
+    /// Keep an object or array on one line if it fits in `width` columns
+    ///
+    /// Columns are counted in characters, a tab being one
+    pub fn max_width(mut self, width : usize) -> PrettyConfig
+    {
+        self.max_width = Some(width);
+        self
+    }
+
+    /// End the output with a line ending
+    pub fn final_newline(mut self, newline : bool) -> PrettyConfig
+    {
+        self.final_newline = newline;
+        self
+    }
+}
+
+impl Default for PrettyConfig
+{
+    fn default() -> PrettyConfig
+    {
+        PrettyConfig::new()
+    }
+}
+
+impl Escaping
+{
+    /// Escape only quotation marks, reverse solidi and control characters
+    pub fn minimal() -> Escaping
+    {
+        Escaping::default()
+    }
+
+    /// Escape every non-ASCII character as `\uXXXX`, outside the Basic
+    /// Multilingual Plane as a surrogate pair, for ASCII-only channels
+    pub fn ascii_only(mut self, ascii : bool) -> Escaping
+    {
+        self.ascii = ascii;
+        self
+    }
+
+    /// Escape `<`, `>`, `&` and `'`, so the output can be embedded in
+    /// HTML, including in a `<script>` element
+    pub fn html_safe(mut self, html : bool) -> Escaping
+    {
+        self.html = html;
+        self
+    }
+
+    /// Escape U+2028 and U+2029, which JavaScript before ES2019 does
+    /// not accept in string literals
+    pub fn js_safe(mut self, line_separators : bool) -> Escaping
+    {
+        self.line_separators = line_separators;
+        self
+    }
+}
+
+impl SerializerOptions
+{
+    pub fn new() -> SerializerOptions
+    {
+        SerializerOptions::default()
+    }
+
+    /// Write pretty-printed JSON laid out by `config`
+    pub fn pretty(mut self, config : PrettyConfig) -> SerializerOptions
+    {
+        self.pretty = Some(config);
+        self
+    }
+
+    pub fn escaping(mut self, escaping : Escaping) -> SerializerOptions
+    {
+        self.escaping = escaping;
+        self
+    }
+
+    pub fn number_format(mut self, format : NumberFormat) -> SerializerOptions
+    {
+        self.numbers = format;
+        self
+    }
+
+    /// What to write for NaN and the infinities, by default an error
+    pub fn non_finite(mut self, policy : NonFinite) -> SerializerOptions
+    {
+        self.non_finite = policy;
+        self
+    }
+}
+
+/// Values that can be written as JSON text
+///
+/// Output is compact RFC 8259 JSON unless the options say otherwise:
+/// no whitespace, strings escaped only where JSON requires it and
+/// numbers in their shortest form. Writing fails on NaN and infinities.
+pub trait ToJson
+{
+    fn write_json_with<W : Write>(&self, out : &mut W, options : &SerializerOptions) -> Result<(), SerializeError>;
+
+    fn write_json<W : Write>(&self, out : &mut W) -> Result<(), SerializeError>
+    {
+        self.write_json_with(out, &SerializerOptions::new())
+    }
+
+    fn to_json(&self) -> Result<String, SerializeError>
+    {
+        self.to_json_with(&SerializerOptions::new())
+    }
+
+    fn to_json_with(&self, options : &SerializerOptions) -> Result<String, SerializeError>
+    {
+        let mut out = String::new();
+        self.write_json_with(&mut out, options)?;
+        Ok(out)
+    }
+
+    fn to_json_pretty(&self, config : PrettyConfig) -> Result<String, SerializeError>
+    {
+        self.to_json_with(&SerializerOptions::new().pretty(config))
+    }
+
+    /// Write to a byte sink such as a file or socket
+    fn write_json_to<W : io::Write>(&self, writer : &mut W) -> Result<(), SerializeError>
+    {
+        self.write_json_to_with(writer, &SerializerOptions::new())
+    }
+
+    fn write_json_to_with<W : io::Write>(&self, writer : &mut W, options : &SerializerOptions) -> Result<(), SerializeError>
+    {
+        let mut adapter = IoAdapter{writer, error : None};
+        match self.write_json_with(&mut adapter, options)
+        {
+            Err(SerializeError::Fmt(error)) => Err(adapter.error.map_or(SerializeError::Fmt(error), SerializeError::Io)),
+            result => result,
+        }
+    }
+}
+
+impl ToJson for JsonValue
+{
+    fn write_json_with<W : Write>(&self, out : &mut W, options : &SerializerOptions) -> Result<(), SerializeError>
+    {
+        let mut serializer = Serializer::new(out, options);
+        serializer.write(Some(self), Vec::new())?;
+        serializer.finish()
+    }
+}
+
+impl ToJson for JsonObject
+{
+    fn write_json_with<W : Write>(&self, out : &mut W, options : &SerializerOptions) -> Result<(), SerializeError>
+    {
+        let mut serializer = Serializer::new(out, options);
+        serializer.write_object(self)?;
+        serializer.finish()
+    }
+}
+
+impl ToJson for JsonArray
+{
+    fn write_json_with<W : Write>(&self, out : &mut W, options : &SerializerOptions) -> Result<(), SerializeError>
+    {
+        let mut serializer = Serializer::new(out, options);
+        serializer.write_array(self)?;
+        serializer.finish()
+    }
+}
+
+/// Writes compact JSON; NaN and infinities, which `Display` cannot
+/// report, are written as `null` as JavaScript's `JSON.stringify` does
+impl fmt::Display for JsonValue
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let options = SerializerOptions::new();
+        let mut serializer = Serializer::new(f, &options);
+        serializer.lenient = true;
+        serializer.write(Some(self), Vec::new()).map_err(|_| fmt::Error)
+    }
+}
+
+/// Writes compact JSON, see the `Display` of `JsonValue`
+impl fmt::Display for JsonObject
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let options = SerializerOptions::new();
+        let mut serializer = Serializer::new(f, &options);
+        serializer.lenient = true;
+        serializer.write_object(self).map_err(|_| fmt::Error)
+    }
+}
+
+/// Sends formatted output to an `io::Write`, keeping the error it fails with
+pub(crate) struct IoAdapter<'w, W : 'w>
+{
+    pub(crate) writer : &'w mut W,
+    pub(crate) error : Option<io::Error>,
+}
+
+impl<'w, W : io::Write> Write for IoAdapter<'w, W>
+{
+    fn write_str(&mut self, s : &str) -> fmt::Result
+    {
+        match self.writer.write_all(s.as_bytes())
+        {
+            Ok(()) => Ok(()),
+            Err(error) =>
+            {
+                self.error = Some(error);
+                Err(fmt::Error)
+            },
+        }
+    }
+}
+
+/// Counts the characters written, failing once there are more than `remaining`
+struct Measure
+{
+    remaining : usize,
+}
+
+impl Write for Measure
+{
+    fn write_str(&mut self, s : &str) -> fmt::Result
+    {
+        let length = s.chars().count();
+        if length > self.remaining
+        {
+            return Err(fmt::Error);
+        }
+        self.remaining -= length;
+        Ok(())
+    }
+}
+
+/// The members or elements left to write of an object or array
+enum Open<'a>
+{
+    Object(map::Iter<'a, JsonValue>),
+    Sorted(vec::IntoIter<(&'a String, &'a JsonValue)>),
+    Array(slice::Iter<'a, JsonValue>),
+}
+
+/// An object or array being written
+struct Frame<'a>
+{
+    open : Open<'a>,
+    started : bool,
+    /// Written on a single line
+    inline : bool,
+}
+
+struct Serializer<'o, 'w, W : 'w>
+{
+    out : &'w mut W,
+    options : &'o SerializerOptions,
+    /// Write non-finite numbers as `null` rather than failing
+    lenient : bool,
+    /// Write every container on a single line
+    inline : bool,
+    /// Characters written since the last line ending, if a maximum width needs it
+    column : Option<usize>,
+    /// Levels of indentation around the value being written
+    base : usize,
+}
+
+/// Output goes through the serializer, which tracks the column
+impl<'o, 'w, W : Write> Write for Serializer<'o, 'w, W>
+{
+    fn write_str(&mut self, s : &str) -> fmt::Result
+    {
+        if let Some(ref mut column) = self.column
+        {
+            match s.rfind('\n')
+            {
+                Some(i) => *column = s[i + 1..].chars().count(),
+                None => *column += s.chars().count(),
+            }
+        }
+        self.out.write_str(s)
+    }
+}
+
+impl<'o, 'w, W : Write> Serializer<'o, 'w, W>
+{
+    fn new(out : &'w mut W, options : &'o SerializerOptions) -> Serializer<'o, 'w, W>
+    {
+        let column = options.pretty.as_ref().and_then(|pretty| pretty.max_width).map(|_| 0);
+        Serializer{out, options, lenient : false, inline : false, column, base : 0}
+    }
+
+    fn write_object(&mut self, object : &JsonObject) -> Result<(), SerializeError>
+    {
+        if object.is_empty()
+        {
+            self.write_str("{}")?;
+            return Ok(());
+        }
+        let inline = self.fits(|serializer| serializer.write_object(object));
+        self.write_char('{')?;
+        let frame = Frame{open : self.open_object(object), started : false, inline};
+        self.write(None, vec![frame])
+    }
+
+    fn write_array(&mut self, array : &JsonArray) -> Result<(), SerializeError>
+    {
+        if array.is_empty()
+        {
+            self.write_str("[]")?;
+            return Ok(());
+        }
+        let inline = self.fits(|serializer| serializer.write_array(array));
+        self.write_char('[')?;
+        let frame = Frame{open : Open::Array(array.iter()), started : false, inline};
+        self.write(None, vec![frame])
+    }
+
+    /// Ends the output of a whole document
+    fn finish(&mut self) -> Result<(), SerializeError>
+    {
+        if let Some(pretty) = self.pretty()
+        {
+            if pretty.final_newline
+            {
+                self.new_line(0)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn pretty(&self) -> Option<&'o PrettyConfig>
+    {
+        self.options.pretty.as_ref()
+    }
+
+    fn open_object<'a>(&self, object : &'a JsonObject) -> Open<'a>
+    {
+        match self.pretty()
+        {
+            Some(pretty) if pretty.sort_keys =>
+            {
+                let mut members : Vec<_> = object.iter().collect();
+                members.sort_by(|a, b| a.0.cmp(b.0));
+                Open::Sorted(members.into_iter())
+            },
+            _ => Open::Object(object.iter()),
+        }
+    }
+
+    /// Write `next` if there is one, then the rest of the containers
+    /// on `stack`, whose opening brackets are already written
+    ///
+    /// Nested values are kept on the explicit stack, so any depth can be written
+    fn write<'a>(&mut self, mut next : Option<&'a JsonValue>, mut stack : Vec<Frame<'a>>) -> Result<(), SerializeError>
+    {
+        loop
+        {
+            if let Some(value) = next.take()
+            {
+                match *value
+                {
+                    JsonValue::JsonObject(ref object) if !object.is_empty() =>
+                    {
+                        let inline = self.stays_inline(value, &stack);
+                        self.write_char('{')?;
+                        stack.push(Frame{open : self.open_object(object), started : false, inline});
+                    },
+                    JsonValue::JsonArray(ref array) if !array.is_empty() =>
+                    {
+                        let inline = self.stays_inline(value, &stack);
+                        self.write_char('[')?;
+                        stack.push(Frame{open : Open::Array(array.iter()), started : false, inline});
+                    },
+                    JsonValue::JsonObject(_) => self.write_str("{}")?,
+                    JsonValue::JsonArray(_) => self.write_str("[]")?,
+                    JsonValue::JsonString(ref string) => write_string(string, self.options.escaping, self)?,
+                    JsonValue::JsonNumber(number) => self.write_number(number)?,
+                    JsonValue::JsonBool(b) => self.write_str(if b {"true"} else {"false"})?,
+                    JsonValue::Null => self.write_str("null")?,
+                    JsonValue::JsonRaw(ref raw) => self.write_raw(raw, &stack)?,
+                }
+            }
+
+            let depth = stack.len();
+            let frame = match stack.last_mut()
+            {
+                None => return Ok(()),
+                Some(frame) => frame,
+            };
+            let member = match frame.open
+            {
+                Open::Object(ref mut members) => members.next().map(|(key, value)| (Some(key), value)),
+                Open::Sorted(ref mut members) => members.next().map(|(key, value)| (Some(key), value)),
+                Open::Array(ref mut elements) => elements.next().map(|value| (None, value)),
+            };
+            let started = frame.started;
+            let inline = frame.inline;
+            frame.started = true;
+
+            match member
+            {
+                Some((key, value)) =>
+                {
+                    if started
+                    {
+                        self.write_char(',')?;
+                    }
+                    self.separate(depth, inline, started)?;
+                    if let Some(key) = key
+                    {
+                        write_string(key, self.options.escaping, self)?;
+                        self.write_char(':')?;
+                        if self.pretty().map_or(false, |pretty| pretty.space_after_colon)
+                        {
+                            self.write_char(' ')?;
+                        }
+                    }
+                    next = Some(value);
+                },
+                None =>
+                {
+                    let close = match stack.pop()
+                    {
+                        Some(Frame{open : Open::Array(_), ..}) => ']',
+                        _ => '}',
+                    };
+                    if !inline
+                    {
+                        self.new_line(depth - 1)?;
+                    }
+                    self.write_char(close)?;
+                },
+            }
+        }
+    }
+
+    /// Write a raw value inside the containers of `stack`
+    ///
+    /// Text read with strict options goes out verbatim when that cannot
+    /// differ from writing its value: no escapes beyond the minimal ones,
+    /// and compact output unless it is a scalar. Otherwise it is parsed and
+    /// written like any value, so extensions and comments do not leak into
+    /// the JSON, strings get the escaping asked for and the layout stays
+    /// consistent
+    fn write_raw(&mut self, raw : &RawValue, stack : &[Frame]) -> Result<(), SerializeError>
+    {
+        let scalar = !raw.as_str().starts_with(&['{', '['][..]);
+        if raw.is_strict() && self.options.escaping == Escaping::minimal() && (scalar || self.pretty().is_none())
+        {
+            self.write_str(raw.as_str())?;
+            return Ok(());
+        }
+        let parsed = raw.parse().map_err(|error| SerializeError::InvalidFragment(format!("{:?}", error)))?;
+        // the parsed text holds no raw values, so this goes one level deep at most
+        let (base, inline) = (self.base, self.inline);
+        self.base += stack.len();
+        self.inline = inline || stack.last().map_or(false, |frame| frame.inline);
+        let result = self.write(Some(&parsed), Vec::new());
+        self.base = base;
+        self.inline = inline;
+        json::drop_nested(parsed);
+        result
+    }
+
+    /// Whitespace before a member or element: a new line, or in a
+    /// pretty container on one line a space after the comma
+    fn separate(&mut self, depth : usize, inline : bool, started : bool) -> fmt::Result
+    {
+        match self.pretty()
+        {
+            Some(_) if !inline => self.new_line(depth),
+            Some(_) if started => self.write_char(' '),
+            _ => Ok(()),
+        }
+    }
+
+    fn new_line(&mut self, depth : usize) -> fmt::Result
+    {
+        match self.pretty()
+        {
+            Some(pretty) =>
+            {
+                let depth = depth + self.base;
+                new_line(pretty, depth, self)
+            },
+            None => Ok(()),
+        }
+    }
+
+    /// True if the object or array `value`, starting inside the
+    /// containers of `stack`, is written on one line
+    fn stays_inline(&self, value : &JsonValue, stack : &[Frame]) -> bool
+    {
+        stack.last().map_or(false, |frame| frame.inline) || self.fits(|serializer| serializer.write(Some(value), Vec::new()))
+    }
+
+    /// True if what `write` writes is on one line: always in compact
+    /// output, and in pretty output if it fits in the maximum width
+    fn fits<F>(&self, write : F) -> bool
+        where F : FnOnce(&mut Serializer<Measure>) -> Result<(), SerializeError>
+    {
+        if self.inline || self.pretty().is_none()
+        {
+            return true;
+        }
+        let (width, column) = match (self.pretty().and_then(|pretty| pretty.max_width), self.column)
+        {
+            (Some(width), Some(column)) => (width, column),
+            _ => return false,
+        };
+        let mut measure = Measure{remaining : width.saturating_sub(column)};
+        let mut serializer = Serializer{out : &mut measure, options : self.options, lenient : true, inline : true, column : None, base : 0};
+        write(&mut serializer).is_ok()
+    }
+
+    fn write_number(&mut self, number : f64) -> Result<(), SerializeError>
+    {
+        if !number.is_finite()
+        {
+            match self.options.non_finite
+            {
+                NonFinite::Error if !self.lenient => return Err(SerializeError::NonFiniteNumber(number)),
+                NonFinite::Error | NonFinite::Null => self.write_str("null")?,
+                NonFinite::String if number.is_nan() => self.write_str("\"NaN\"")?,
+                NonFinite::String if number > 0.0 => self.write_str("\"Infinity\"")?,
+                NonFinite::String => self.write_str("\"-Infinity\"")?,
+            }
+            return Ok(());
+        }
+        match self.options.numbers
+        {
+            NumberFormat::Shortest => write_shortest(number, self)?,
+            NumberFormat::Fixed(precision) => write!(self, "{:.*}", precision, number)?,
+            NumberFormat::Exponent => write!(self, "{:e}", number)?,
+        }
+        Ok(())
+    }
+}
+
+/// Writes a finite number with the fewest digits that read back as it
+///
+/// Rust's formatting of `f64` already gives the shortest round-trip
+/// digits; only the notation is chosen here.
+pub(crate) fn write_shortest<W : Write>(number : f64, out : &mut W) -> fmt::Result
+{
+    let magnitude = number.abs();
+    if magnitude == 0.0 || (1e-6..1e21).contains(&magnitude)
+    {
+        write!(out, "{}", number)
+    }
+    else
+    {
+        write!(out, "{:e}", number)
+    }
+}
+
+/// Writes a line ending and the indentation of `depth` levels
+pub(crate) fn new_line<W : Write>(pretty : &PrettyConfig, depth : usize, out : &mut W) -> fmt::Result
+{
+    out.write_str(match pretty.line_ending {LineEnding::Lf => "\n", LineEnding::CrLf => "\r\n"})?;
+    for _ in 0..depth
+    {
+        match pretty.indent
+        {
+            Indent::Spaces(width) => for _ in 0..width {out.write_char(' ')?},
+            Indent::Tab => out.write_char('\t')?,
+        }
+    }
+    Ok(())
+}
+
+/// Writes `value` nested `depth` levels deep, starting at `column`
+pub(crate) fn write_nested<W : Write>(value : &JsonValue, out : &mut W, options : &SerializerOptions,
+                                      depth : usize, column : usize) -> Result<(), SerializeError>
+{
+    let mut serializer = Serializer::new(out, options);
+    serializer.base = depth;
+    serializer.column = serializer.column.map(|_| column);
+    serializer.write(Some(value), Vec::new())
+}
+
+/// Writes a string with the escapes RFC 8259 requires: quotation mark,
+/// reverse solidus and control characters, and those `escaping` adds
+pub(crate) fn write_string<W : Write>(string : &str, escaping : Escaping, out : &mut W) -> fmt::Result
+{
+    out.write_char('"')?;
+    let mut start = 0;
+    for (i, c) in string.char_indices()
+    {
+        let escape = match c
+        {
+            '"' => "\\\"",
+            '\\' => "\\\\",
+            '\n' => "\\n",
+            '\r' => "\\r",
+            '\t' => "\\t",
+            '\u{8}' => "\\b",
+            '\u{c}' => "\\f",
+            '\u{0}'..='\u{1f}' => "",
+            '<' | '>' | '&' | '\'' if escaping.html => "",
+            '\u{2028}' | '\u{2029}' if escaping.line_separators => "",
+            '\u{80}'..='\u{10ffff}' if escaping.ascii => "",
+            _ => continue,
+        };
+        out.write_str(&string[start..i])?;
+        if escape.is_empty()
+        {
+            let mut units = [0; 2];
+            for unit in c.encode_utf16(&mut units).iter()
+            {
+                write!(out, "\\u{:04x}", unit)?;
+            }
+        }
+        else
+        {
+            out.write_str(escape)?;
+        }
+        start = i + c.len_utf8();
+    }
+    out.write_str(&string[start..])?;
+    out.write_char('"')
+}
@@ -0,0 +1,257 @@
+use options::DuplicateKeys;
+use pointer::JsonPointer;
+use std::collections::HashMap;
+
+/// A position in source text
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct Location
+{
+    /// Byte offset, counting from 0
+    pub offset : usize,
+    /// Line, counting from 1
+    pub line : usize,
+    /// Column in characters, counting from 1
+    pub column : usize,
+}
+
+/// The text from `start` up to but not including `end`
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct Span
+{
+    pub start : Location,
+    pub end : Location,
+}
+
+impl Span
+{
+    pub fn contains(&self, offset : usize) -> bool
+    {
+        self.start.offset <= offset && offset < self.end.offset
+    }
+}
+
+/// Where a value, and for a member its key, is written
+#[derive(Debug,Clone,PartialEq)]
+pub struct SpanEntry
+{
+    pub path : JsonPointer,
+    pub value : Span,
+    pub key : Option<Span>,
+    /// Position of the enclosing object or array's entry
+    parent : Option<usize>,
+}
+
+/// The spans of every value and key of a document
+///
+/// Entries are in source order; paths are looked up through a hash
+/// index and offsets by binary search, then up the enclosing values.
+/// A repeated key maps to the member the parser's policy keeps.
+#[derive(Debug,Clone,Default)]
+pub struct SourceMap
+{
+    entries : Vec<SpanEntry>,
+    index : HashMap<JsonPointer, usize>,
+}
+
+impl SourceMap
+{
+    /// Span of the value at `path`
+    pub fn value_span(&self, path : &JsonPointer) -> Option<&Span>
+    {
+        self.entry(path).map(|entry| &entry.value)
+    }
+
+    /// Span of the key of the member at `path`
+    pub fn key_span(&self, path : &JsonPointer) -> Option<&Span>
+    {
+        self.entry(path).and_then(|entry| entry.key.as_ref())
+    }
+
+    pub fn entry(&self, path : &JsonPointer) -> Option<&SpanEntry>
+    {
+        self.index.get(path).map(|&i| &self.entries[i])
+    }
+
+    /// The innermost value, or member whose key, contains `offset`
+    pub fn path_at(&self, offset : usize) -> Option<&JsonPointer>
+    {
+        // the last entry starting at or before offset, by value or key
+        let last = match self.entries.binary_search_by_key(&offset, entry_start)
+        {
+            Ok(i) => i,
+            Err(0) => return None,
+            Err(i) => i - 1,
+        };
+        let mut next = Some(last);
+        while let Some(i) = next
+        {
+            let entry = &self.entries[i];
+            if entry.value.contains(offset) || entry.key.map_or(false, |key| key.contains(offset))
+            {
+                return Some(&entry.path);
+            }
+            next = entry.parent;
+        }
+        None
+    }
+
+    /// All entries in source order
+    pub fn entries(&self) -> &[SpanEntry]
+    {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize
+    {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.entries.is_empty()
+    }
+}
+
+fn entry_start(entry : &SpanEntry) -> usize
+{
+    entry.key.map_or(entry.value.start.offset, |key| key.start.offset)
+}
+
+/// Records the spans of the values a parser builds, as it builds them
+pub(crate) struct SpanRecorder<'src>
+{
+    map : SourceMap,
+    position : Position<'src>,
+    policy : DuplicateKeys,
+    /// Values started but not finished, whether their paths are indexed,
+    /// and for an array the number of its elements so far
+    open : Vec<(usize, bool, usize)>,
+}
+
+impl<'src> SpanRecorder<'src>
+{
+    pub(crate) fn new(source : &'src str, policy : DuplicateKeys) -> SpanRecorder<'src>
+    {
+        SpanRecorder{map : SourceMap::default(), position : Position::new(source), policy, open : Vec::new()}
+    }
+
+    /// Record a value starting at byte `offset`, a member of the innermost
+    /// open object if `key` gives its name and byte range, else an element
+    /// of the innermost open array or the root
+    ///
+    /// Offsets must increase from call to call, ends included
+    pub(crate) fn start(&mut self, offset : usize, key : Option<(&str, usize, usize)>)
+    {
+        let (path, key, parent, indexed) = match self.open.last_mut()
+        {
+            Some(&mut (entry, indexed, ref mut elements)) =>
+            {
+                let mut path = self.map.entries[entry].path.clone();
+                let key = match key
+                {
+                    Some((name, start, end)) =>
+                    {
+                        path.push(name);
+                        let start = self.position.at(start);
+                        Some(Span{start, end : self.position.at(end)})
+                    },
+                    None =>
+                    {
+                        path.push(&elements.to_string());
+                        *elements += 1;
+                        None
+                    },
+                };
+                (path, key, Some(entry), indexed)
+            },
+            None => (JsonPointer::root(), None, None, true),
+        };
+        // after the key, which comes first
+        let location = self.position.at(offset);
+
+        let entry = self.map.entries.len();
+        let indexed = indexed && match self.map.index.get(&path).cloned()
+        {
+            // a repeated key: the policy decides which member the value holds
+            Some(_) if self.policy == DuplicateKeys::FirstWins => false,
+            Some(earlier) =>
+            {
+                self.unindex(earlier);
+                true
+            },
+            None => true,
+        };
+        if indexed
+        {
+            self.map.index.insert(path.clone(), entry);
+        }
+        self.map.entries.push(SpanEntry{path, value : Span{start : location, end : location}, key, parent});
+        self.open.push((entry, indexed, 0));
+    }
+
+    /// Record the end of the innermost open value at byte `offset`
+    pub(crate) fn end(&mut self, offset : usize)
+    {
+        if let Some((entry, _, _)) = self.open.pop()
+        {
+            self.map.entries[entry].value.end = self.position.at(offset);
+        }
+    }
+
+    pub(crate) fn finish(self) -> SourceMap
+    {
+        self.map
+    }
+
+    /// Remove the finished value at `entry`, and everything in it, from the index
+    fn unindex(&mut self, entry : usize)
+    {
+        let end = self.map.entries[entry].value.end.offset;
+        for (i, inner) in self.map.entries.iter().enumerate().skip(entry)
+        {
+            if i > entry && entry_start(inner) >= end
+            {
+                break;
+            }
+            if self.map.index.get(&inner.path) == Some(&i)
+            {
+                self.map.index.remove(&inner.path);
+            }
+        }
+    }
+}
+
+/// Tracks the line and column of increasing offsets
+pub(crate) struct Position<'src>
+{
+    source : &'src str,
+    offset : usize,
+    line : usize,
+    column : usize,
+}
+
+impl<'src> Position<'src>
+{
+    pub(crate) fn new(source : &'src str) -> Position<'src>
+    {
+        Position{source, offset : 0, line : 1, column : 1}
+    }
+
+    pub(crate) fn at(&mut self, offset : usize) -> Location
+    {
+        for c in self.source[self.offset..offset].chars()
+        {
+            if c == '\n'
+            {
+                self.line += 1;
+                self.column = 1;
+            }
+            else
+            {
+                self.column += 1;
+            }
+        }
+        self.offset = offset;
+        Location{offset, line : self.line, column : self.column}
+    }
+}
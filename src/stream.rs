@@ -0,0 +1,307 @@
+use json::JsonValue;
+use parse_error::ErrorKind;
+use parser::JsonParser;
+use std::error::Error;
+use std::io::{self, Read};
+use std::{fmt, result, str};
+
+const CHUNK_SIZE : usize = 8 * 1024;
+
+/// An error while streaming the elements of a top-level array
+#[derive(Debug)]
+pub enum StreamError
+{
+    /// Reading the input failed, iteration stops
+    Io(io::Error),
+    /// The array itself is malformed or truncated, iteration stops
+    Syntax{offset : usize, message : String},
+    /// A single element failed to parse, iteration continues with the next element
+    ///
+    /// `offset` is the byte offset in the input of the token at fault
+    Element{index : usize, offset : usize, kind : ErrorKind},
+    /// A single element is not valid UTF-8, from byte `offset` of the input on;
+    /// iteration continues with the next element
+    InvalidUtf8{index : usize, offset : usize},
+}
+
+impl fmt::Display for StreamError
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self
+        {
+            StreamError::Io(ref error) => write!(f, "I/O error: {}", error),
+            StreamError::Syntax{offset, ref message} => write!(f, "{} at byte {}", message, offset),
+            StreamError::Element{index, offset, kind} =>
+            {
+                write!(f, "Element {}: {} at byte {}", index, kind, offset)
+            },
+            StreamError::InvalidUtf8{index, offset} => write!(f, "Element {}: invalid UTF-8 at byte {}", index, offset),
+        }
+    }
+}
+
+impl Error for StreamError
+{
+    fn description(&self) -> &str {
+        "Error while streaming array elements"
+    }
+}
+
+enum State
+{
+    Start,
+    First,
+    Element,
+    End,
+    Done,
+}
+
+/// A lazy iterator over the elements of a top-level JSON array
+///
+/// Reads the input in chunks and yields each element as its own
+/// `JsonValue` as soon as it is complete, so memory use is bounded
+/// by the largest element rather than by the whole document.
+/// A malformed element is reported as `StreamError::Element` and
+/// iteration goes on with the next one.
+pub struct ArrayElements<R>
+{
+    reader : R,
+    buffer : Vec<u8>,
+    offset : usize,
+    index : usize,
+    state : State,
+}
+
+impl<'a> ArrayElements<&'a [u8]>
+{
+    /// Iterate over the elements of an array held in memory
+    pub fn from_source(source : &'a str) -> ArrayElements<&'a [u8]>
+    {
+        ArrayElements::new(source.as_bytes())
+    }
+}
+
+impl<R : Read> ArrayElements<R>
+{
+    /// Iterate over the elements of an array read from `reader`
+    pub fn new(reader : R) -> ArrayElements<R>
+    {
+        ArrayElements
+        {
+            reader,
+            buffer : Vec::new(),
+            offset : 0,
+            index : 0,
+            state : State::Start,
+        }
+    }
+
+    /// Read another chunk into the buffer, false at the end of input
+    fn fill(&mut self) -> io::Result<bool>
+    {
+        let len = self.buffer.len();
+        self.buffer.resize(len + CHUNK_SIZE, 0);
+        loop
+        {
+            match self.reader.read(&mut self.buffer[len..])
+            {
+                Ok(n) =>
+                {
+                    self.buffer.truncate(len + n);
+                    return Ok(n > 0);
+                },
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {},
+                Err(e) =>
+                {
+                    self.buffer.truncate(len);
+                    return Err(e);
+                },
+            }
+        }
+    }
+
+    fn consume(&mut self, n : usize)
+    {
+        self.buffer.drain(..n);
+        self.offset += n;
+    }
+
+    /// Skip whitespace, returning the next byte without consuming it
+    fn skip_whitespace(&mut self) -> io::Result<Option<u8>>
+    {
+        loop
+        {
+            match self.buffer.iter().position(|b| ![b' ', b'\t', b'\r', b'\n'].contains(b))
+            {
+                Some(n) =>
+                {
+                    self.consume(n);
+                    return Ok(Some(self.buffer[0]));
+                },
+                None =>
+                {
+                    let n = self.buffer.len();
+                    self.consume(n);
+                    if !self.fill()?
+                    {
+                        return Ok(None);
+                    }
+                },
+            }
+        }
+    }
+
+    /// Find the ',' or ']' ending the current element,
+    /// returning its position in the buffer
+    fn scan_element(&mut self) -> io::Result<Option<(usize, u8)>>
+    {
+        let mut depth = 0usize;
+        let mut in_string = false;
+        let mut escape = false;
+        let mut i = 0;
+        loop
+        {
+            while i < self.buffer.len()
+            {
+                let b = self.buffer[i];
+                if in_string
+                {
+                    if escape
+                    {
+                        escape = false;
+                    }
+                    else if b == b'\\'
+                    {
+                        escape = true;
+                    }
+                    else if b == b'"'
+                    {
+                        in_string = false;
+                    }
+                }
+                else
+                {
+                    match b
+                    {
+                        b'"' => in_string = true,
+                        b'[' | b'{' => depth += 1,
+                        b']' | b'}' if depth > 0 => depth -= 1,
+                        b',' | b']' if depth == 0 => return Ok(Some((i, b))),
+                        _ => {},
+                    }
+                }
+                i += 1;
+            }
+            if !self.fill()?
+            {
+                return Ok(None);
+            }
+        }
+    }
+
+    fn fail(&mut self, error : StreamError) -> Option<result::Result<JsonValue, StreamError>>
+    {
+        self.state = State::Done;
+        Some(Err(error))
+    }
+
+    fn syntax_error(&self, message : &str) -> StreamError
+    {
+        StreamError::Syntax{offset : self.offset, message : message.to_owned()}
+    }
+
+    fn read_element(&mut self) -> result::Result<JsonValue, StreamError>
+    {
+        let (end, delimiter) = match self.scan_element()
+        {
+            Ok(Some(found)) => found,
+            Ok(None) =>
+            {
+                self.state = State::Done;
+                return Err(self.syntax_error("Unexpected end of input"));
+            },
+            Err(e) =>
+            {
+                self.state = State::Done;
+                return Err(StreamError::Io(e));
+            },
+        };
+
+        let index = self.index;
+        let offset = self.offset;
+        let result = match str::from_utf8(&self.buffer[..end])
+        {
+            Ok(text) => JsonParser::new(text).parse().map_err(|e|
+            {
+                let at = e.span().as_ptr() as usize - text.as_ptr() as usize;
+                StreamError::Element{index, offset : offset + at, kind : e.kind()}
+            }),
+            Err(e) => Err(StreamError::InvalidUtf8{index, offset : offset + e.valid_up_to()}),
+        };
+
+        self.consume(end + 1);
+        self.index += 1;
+        self.state = if delimiter == b',' {State::Element} else {State::End};
+        result
+    }
+}
+
+impl<R : Read> Iterator for ArrayElements<R>
+{
+    type Item = result::Result<JsonValue, StreamError>;
+
+    fn next(&mut self) -> Option<Self::Item>
+    {
+        loop
+        {
+            match self.state
+            {
+                State::Start => match self.skip_whitespace()
+                {
+                    Ok(Some(b'[')) =>
+                    {
+                        self.consume(1);
+                        self.state = State::First;
+                    },
+                    Ok(_) =>
+                    {
+                        let error = self.syntax_error("Expected '['");
+                        return self.fail(error);
+                    },
+                    Err(e) => return self.fail(StreamError::Io(e)),
+                },
+                State::First => match self.skip_whitespace()
+                {
+                    Ok(Some(b']')) =>
+                    {
+                        self.consume(1);
+                        self.state = State::End;
+                    },
+                    Ok(Some(_)) => self.state = State::Element,
+                    Ok(None) =>
+                    {
+                        let error = self.syntax_error("Unexpected end of input");
+                        return self.fail(error);
+                    },
+                    Err(e) => return self.fail(StreamError::Io(e)),
+                },
+                State::Element => return Some(self.read_element()),
+                State::End => match self.skip_whitespace()
+                {
+                    Ok(None) =>
+                    {
+                        self.state = State::Done;
+                        return None;
+                    },
+                    Ok(Some(_)) =>
+                    {
+                        let error = self.syntax_error("Unexpected data after the array");
+                        return self.fail(error);
+                    },
+                    Err(e) => return self.fail(StreamError::Io(e)),
+                },
+                State::Done => return None,
+            }
+        }
+    }
+}
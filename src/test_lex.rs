@@ -1,4 +1,6 @@
 use lex::{Lex,Token,TokenType};
+use options::ParserOptions;
+use std::f64;
 
 // assert_eq!(lexer.next(),Token{span: &json[], token_type: } );
 
@@ -121,3 +123,30 @@ fn test_special_chars()
     let mut lexer = Lex::new(json);
     assert_eq!(lexer.next(),Token{span: &json[0..6], token_type : TokenType::String("¶α".to_string())});
 }
+
+#[test]
+fn test_options()
+{
+    let json = "// line\n'single' /* block */ +1 .5 0x1F -Infinity";
+    let mut lexer = Lex::new(json);
+    assert_eq!(lexer.next(),Token{span: &json[0..1], token_type : TokenType::Error});
+
+    let options = ParserOptions::new().comments(true).single_quotes(true).leading_plus(true)
+        .leading_decimal_point(true).hex_numbers(true).nan_infinity(true);
+    let mut lexer = Lex::new(json).with_options(options);
+    assert_eq!(lexer.next(),Token{span: &json[8..16], token_type : TokenType::String("single".to_string())});
+    assert_eq!(lexer.next(),Token{span: &json[29..31], token_type : TokenType::Number(1.0)});
+    assert_eq!(lexer.next(),Token{span: &json[32..34], token_type : TokenType::Number(0.5)});
+    assert_eq!(lexer.next(),Token{span: &json[35..39], token_type : TokenType::Number(31.0)});
+    assert_eq!(lexer.next(),Token{span: &json[40..49], token_type : TokenType::Number(-f64::INFINITY)});
+    assert_eq!(lexer.next(),Token{span: "", token_type : TokenType::End});
+
+    let json = "{key_1: 'it\\'s', null:1}";
+    let mut lexer = Lex::new(json).with_options(ParserOptions::new().unquoted_keys(true).single_quotes(true));
+    assert_eq!(lexer.next(),Token{span: &json[0..1], token_type : TokenType::LeftBrace});
+    assert_eq!(lexer.next(),Token{span: &json[1..6], token_type : TokenType::Identifier("key_1".to_string())});
+    assert_eq!(lexer.next(),Token{span: &json[6..7], token_type : TokenType::Colon});
+    assert_eq!(lexer.next(),Token{span: &json[8..15], token_type : TokenType::String("it's".to_string())});
+    assert_eq!(lexer.next(),Token{span: &json[15..16], token_type : TokenType::Comma});
+    assert_eq!(lexer.next(),Token{span: &json[17..21], token_type : TokenType::Null});
+}
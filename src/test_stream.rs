@@ -0,0 +1,115 @@
+use extract::extract;
+use json::*;
+use pointer::{JsonPointer,PathPattern};
+use stream::{ArrayElements,StreamError};
+use parse_error::ErrorKind;
+use std::io::{self,Read};
+
+fn patterns(patterns : &[&str]) -> Vec<PathPattern>
+{
+    patterns.iter().map(|p| PathPattern::parse(p).unwrap()).collect()
+}
+
+#[test]
+fn test_extract_exact() {
+    let source = r#" { "meta" : { "version" : "1.2", "tags" : ["a", "b"] }, "items" : [ {"id" : 1}, {"id" : 2} ] } "#;
+    let found = extract(source, &patterns(&["/meta/version", "/meta/tags/1"])).unwrap();
+    assert_eq!(found, vec![
+        (JsonPointer::parse("/meta/version").unwrap(), JsonValue::JsonString("1.2".to_owned())),
+        (JsonPointer::parse("/meta/tags/1").unwrap(), JsonValue::JsonString("b".to_owned())),
+    ]);
+
+    // the document is not read past the last exact match
+    let source = r#" { "meta" : { "version" : 3 }, "items" : [ this is never read "#;
+    let found = extract(source, &patterns(&["/meta/version"])).unwrap();
+    assert_eq!(found, vec![(JsonPointer::parse("/meta/version").unwrap(), JsonValue::JsonNumber(3.0))]);
+}
+
+#[test]
+fn test_extract_wildcard() {
+    let source = r#" { "items" : [ {"id" : 1, "x" : [1,{}]}, {"name" : "no id"}, {"id" : "a\"b"} ], "id" : 0 } "#;
+    let found = extract(source, &patterns(&["/items/*/id"])).unwrap();
+    assert_eq!(found, vec![
+        (JsonPointer::parse("/items/0/id").unwrap(), JsonValue::JsonNumber(1.0)),
+        (JsonPointer::parse("/items/2/id").unwrap(), JsonValue::JsonString("a\"b".to_owned())),
+    ]);
+
+    let found = extract(source, &patterns(&["/items/1", "/items/1/name"])).unwrap();
+    let mut object = JsonObject::new();
+    object.insert("name".to_owned(), JsonValue::JsonString("no id".to_owned()));
+    assert_eq!(found, vec![
+        (JsonPointer::parse("/items/1").unwrap(), JsonValue::JsonObject(object)),
+        (JsonPointer::parse("/items/1/name").unwrap(), JsonValue::JsonString("no id".to_owned())),
+    ]);
+
+    // skipped parts are still syntax checked
+    assert!(extract(r#" { "items" : [], "other" : [1,,2] } "#, &patterns(&["/items/*"])).is_err());
+    assert!(extract(r#" [1] 2 "#, &patterns(&["/*"])).is_err());
+}
+
+#[test]
+fn test_pointer() {
+    let pointer = JsonPointer::parse("/a~1b/~0/0").unwrap();
+    assert_eq!(pointer.segments(), &["a/b".to_owned(), "~".to_owned(), "0".to_owned()]);
+    assert_eq!(pointer.to_string(), "/a~1b/~0/0");
+    assert!(JsonPointer::parse("").unwrap().is_root());
+    assert!(JsonPointer::parse("a").is_err());
+    assert!(JsonPointer::parse("/~2").is_err());
+    assert!(PathPattern::parse("/*/x").unwrap().matches(&JsonPointer::parse("/y/x").unwrap()));
+}
+
+/// A reader handing out the input a few bytes at a time
+struct Trickle<'a>(&'a [u8]);
+
+impl<'a> Read for Trickle<'a>
+{
+    fn read(&mut self, buf : &mut [u8]) -> io::Result<usize>
+    {
+        let n = buf.len().min(self.0.len()).min(3);
+        buf[..n].copy_from_slice(&self.0[..n]);
+        self.0 = &self.0[n..];
+        Ok(n)
+    }
+}
+
+#[test]
+fn test_array_elements() {
+    let source = r#" [ {"a" : "],}"}, [1, [2]] , "x\"", null ] "#;
+    let mut object = JsonObject::new();
+    object.insert("a".to_owned(), JsonValue::JsonString("],}".to_owned()));
+    let expected = vec![
+        JsonValue::JsonObject(object),
+        JsonValue::JsonArray(vec![JsonValue::JsonNumber(1.0), JsonValue::JsonArray(vec![JsonValue::JsonNumber(2.0)])]),
+        JsonValue::JsonString("x\"".to_owned()),
+        JsonValue::Null,
+    ];
+
+    let elements : Vec<JsonValue> = ArrayElements::from_source(source).map(|e| e.unwrap()).collect();
+    assert_eq!(elements, expected);
+    let elements : Vec<JsonValue> = ArrayElements::new(Trickle(source.as_bytes())).map(|e| e.unwrap()).collect();
+    assert_eq!(elements, expected);
+    assert_eq!(ArrayElements::from_source(" [ ] ").count(), 0);
+}
+
+#[test]
+fn test_array_elements_errors() {
+    // a broken element does not stop the iteration
+    let results : Vec<_> = ArrayElements::new(Trickle(b"[1, tru, {\"a\" 2}, 4]")).collect();
+    assert_eq!(results.len(), 4);
+    assert_eq!(results[0].as_ref().unwrap(), &JsonValue::JsonNumber(1.0));
+    match results[1] { Err(StreamError::Element{index : 1, offset : 4, kind : ErrorKind::UnexpectedToken}) => {}, ref r => panic!("{:?}", r) }
+    match results[2] { Err(StreamError::Element{index : 2, offset : 14, kind : ErrorKind::UnexpectedToken}) => {}, ref r => panic!("{:?}", r) }
+    assert_eq!(results[3].as_ref().unwrap(), &JsonValue::JsonNumber(4.0));
+
+    let results : Vec<_> = ArrayElements::new(&b"[\"\xff\", 2]"[..]).collect();
+    match results[0] { Err(StreamError::InvalidUtf8{index : 0, offset : 2}) => {}, ref r => panic!("{:?}", r) }
+    assert_eq!(results[1].as_ref().unwrap(), &JsonValue::JsonNumber(2.0));
+
+    // a truncated array ends the iteration
+    let results : Vec<_> = ArrayElements::from_source("[1, [2").collect();
+    assert_eq!(results.len(), 2);
+    match results[1] { Err(StreamError::Syntax{..}) => {}, ref r => panic!("{:?}", r) }
+
+    let results : Vec<_> = ArrayElements::from_source("{}").collect();
+    match results[..] { [Err(StreamError::Syntax{offset : 0, ..})] => {}, ref r => panic!("{:?}", r) }
+}
@@ -1,6 +1,24 @@
-use parser::JsonParser;
+use parser::{JsonParser,ValidationStats};
 use json::*;
 use parse_error::*;
+use filter::FieldFilter;
+use limits::ParserLimits;
+use options::{DuplicateKeys,ParserOptions};
+use std::f64;
+use std::cell::Cell;
+use std::rc::Rc;
+use pointer::{JsonPointer,PathPattern};
+use jsonc::{self,CommentKind,Placement};
+use json5;
+use ijson::{self,ViolationKind};
+use jcs;
+use hash::{self,Digest,Fnv1a64,Sha256};
+use cst::{EditError,NodeKind,SyntaxTree};
+use raw::{RawCapture,RawValue};
+use ser::{Escaping,LineEnding,NonFinite,NumberFormat,PrettyConfig,SerializeError,SerializerOptions,ToJson};
+use writer::JsonWriter;
+use std::convert::TryFrom;
+use map::Entry;
 
 #[test]
 fn test_syntax_simple() {
@@ -152,3 +170,1138 @@ fn test_object() {
     assert_eq!(json,result);
 
 }
+
+#[test]
+fn test_filter() {
+    let source = r#" { "id" : 7, "user" : { "name" : "Ann", "bio" : "long text" },
+        "items" : [ { "sku" : "a", "tags" : [1] }, { "sku" : "b", "tags" : "x" } ], "raw" : [1,2,3] } "#;
+    let patterns = |p : &[&str]| p.iter().map(|p| PathPattern::parse(p).unwrap()).collect();
+
+    let json = JsonParser::new(source).with_filter(FieldFilter::Allow(patterns(&["/id", "/user/name", "/items/*/sku"]))).parse().unwrap();
+    let mut user = JsonObject::new();
+    user.insert("name".to_owned(), JsonValue::JsonString("Ann".to_owned()));
+    let mut item_a = JsonObject::new();
+    item_a.insert("sku".to_owned(), JsonValue::JsonString("a".to_owned()));
+    let mut item_b = JsonObject::new();
+    item_b.insert("sku".to_owned(), JsonValue::JsonString("b".to_owned()));
+    let mut object = JsonObject::new();
+    object.insert("id".to_owned(), JsonValue::JsonNumber(7.0));
+    object.insert("user".to_owned(), JsonValue::JsonObject(user));
+    object.insert("items".to_owned(), JsonValue::JsonArray(vec![JsonValue::JsonObject(item_a.clone()), JsonValue::JsonObject(item_b.clone())]));
+    assert_eq!(json, JsonValue::JsonObject(object));
+
+    let json = JsonParser::new(source).with_filter(FieldFilter::Deny(patterns(&["/user", "/items/*/tags", "/raw"]))).parse().unwrap();
+    let mut object = JsonObject::new();
+    object.insert("id".to_owned(), JsonValue::JsonNumber(7.0));
+    object.insert("items".to_owned(), JsonValue::JsonArray(vec![JsonValue::JsonObject(item_a), JsonValue::JsonObject(item_b)]));
+    assert_eq!(json, JsonValue::JsonObject(object));
+
+    let json = JsonParser::new(source).with_filter(FieldFilter::Callback(Box::new(|path| path.segments().len() == 1 && path.segments()[0] != "items"))).parse().unwrap();
+    let object = json.get_object_ref().unwrap();
+    assert!(object.contains_key("id") && object.contains_key("user") && object.contains_key("raw"));
+    assert!(!object.contains_key("items"));
+    assert_eq!(object.get_object_ref("user").unwrap().unwrap().len(), 0);
+
+    // excluded members are still syntax checked
+    let source = r#" { "id" : 7, "user" : { "name" : "Ann", "bio" : tru } } "#;
+    assert!(JsonParser::new(source).with_filter(FieldFilter::Allow(patterns(&["/id"]))).parse().is_err());
+}
+
+#[test]
+fn test_validate() {
+    let source = r#" { "a" : [1, "two", {"three" : [3]}], "b" : null } "#;
+    let stats = JsonParser::new(source).validate().unwrap();
+    assert_eq!(stats, ValidationStats{max_depth : 4, values : 8});
+    assert_eq!(JsonParser::new(" 1 ").validate().unwrap(), ValidationStats{max_depth : 0, values : 1});
+
+    for source in &[r#" { "a" : [1, "two" } "#, r#" [1] "two" "#, r#" { "a" : [], "b" "c" } "#, r#" "unterminated "#, ""]
+    {
+        let validated = JsonParser::new(source).validate().map(|_| ()).map_err(|e| format!("{:?}", e));
+        let parsed = JsonParser::new(source).parse().map(|_| ()).map_err(|e| format!("{:?}", e));
+        assert!(validated.is_err());
+        assert_eq!(validated, parsed);
+    }
+}
+
+#[test]
+fn test_limits() {
+    let kind = |source : &str, limits : ParserLimits| JsonParser::new(source).with_limits(limits).parse().unwrap_err().kind();
+
+    let hostile = "[".repeat(100000);
+    assert_eq!(kind(&hostile, ParserLimits::new().max_depth(64)), ErrorKind::DepthLimitExceeded(64));
+    assert_eq!(JsonParser::new(&hostile).with_limits(ParserLimits::new().max_depth(64)).validate().unwrap_err().kind(),
+        ErrorKind::DepthLimitExceeded(64));
+    assert!(JsonParser::new("[[1]]").with_limits(ParserLimits::new().max_depth(2)).parse().is_ok());
+
+    assert_eq!(kind(" [1, 2] ", ParserLimits::new().max_input_size(4)), ErrorKind::InputSizeLimitExceeded(4));
+    assert_eq!(kind(r#" ["abc", "abcd"] "#, ParserLimits::new().max_string_length(3)), ErrorKind::StringLengthLimitExceeded(3));
+    assert_eq!(kind(r#" {"a" : 1, "b" : 2} "#, ParserLimits::new().max_object_members(1)), ErrorKind::MemberCountLimitExceeded(1));
+    assert_eq!(kind(" [[1, 2], [1, 2, 3]] ", ParserLimits::new().max_array_length(2)), ErrorKind::ArrayLengthLimitExceeded(2));
+    assert_eq!(kind(r#" ["a long string that is over the limit"] "#, ParserLimits::new().max_allocated_bytes(64)),
+        ErrorKind::AllocationLimitExceeded(64));
+
+    let error = JsonParser::new(r#" {"a" : 1, "b" : 2} "#).with_limits(ParserLimits::new().max_object_members(1)).parse().unwrap_err();
+    assert_eq!(error.span(), r#""b""#);
+
+    // an overlong string is not read past the limit, key or value, built or skipped
+    let long = format!("[\"{}\"]", "x".repeat(100000));
+    let error = JsonParser::new(&long).with_limits(ParserLimits::new().max_string_length(8)).parse().unwrap_err();
+    assert_eq!((error.kind(), error.span().len()), (ErrorKind::StringLengthLimitExceeded(8), 10));
+    assert_eq!(JsonParser::new(&long).with_limits(ParserLimits::new().max_string_length(8)).validate().unwrap_err().kind(),
+        ErrorKind::StringLengthLimitExceeded(8));
+    assert_eq!(kind(r#" {"a long key" : 1} "#, ParserLimits::new().max_string_length(8)), ErrorKind::StringLengthLimitExceeded(8));
+    assert!(JsonParser::new(r#" ["\u0041\u0042"] "#).with_limits(ParserLimits::new().max_string_length(2)).parse().is_ok());
+
+    // keys count twice, and raw values count their text
+    let key = format!(r#"{{"{}" : 1}}"#, "k".repeat(100));
+    assert!(JsonParser::new(&key).with_limits(ParserLimits::new().max_allocated_bytes(1000)).parse().is_ok());
+    assert_eq!(kind(&key, ParserLimits::new().max_allocated_bytes(180)), ErrorKind::AllocationLimitExceeded(180));
+    let raw = format!(r#"{{"a" : [{}]}}"#, "1, ".repeat(100) + "1");
+    let capture = || RawCapture::Fields(vec!["a".to_owned()]);
+    assert!(JsonParser::new(&raw).with_raw_capture(capture()).parse().is_ok());
+    let error = JsonParser::new(&raw).with_raw_capture(capture()).with_limits(ParserLimits::new().max_allocated_bytes(200))
+        .parse().unwrap_err();
+    assert_eq!((error.kind(), error.span()), (ErrorKind::AllocationLimitExceeded(200), "["));
+
+    let cancelled = Rc::new(Cell::new(false));
+    let flag = cancelled.clone();
+    let limits = ParserLimits::new().cancel_when(move || flag.get());
+    let mut parser = JsonParser::new(" [[1], [2]] ").with_limits(limits);
+    cancelled.set(true);
+    assert_eq!(parser.parse().unwrap_err().kind(), ErrorKind::Cancelled);
+}
+
+#[test]
+fn test_deep_nesting() {
+    let depth = 100000;
+    let source = format!("{}{}", r#"{"a":["#.repeat(depth), "]}".repeat(depth));
+    assert_eq!(JsonParser::new(&source).validate().unwrap().max_depth, 2 * depth);
+
+    let json = JsonParser::new(&source).parse().unwrap();
+    let mut value = &json;
+    let mut levels = 0;
+    while let JsonValue::JsonObject(ref object) = *value
+    {
+        levels += 1;
+        match object.get("a")
+        {
+            Some(&JsonValue::JsonArray(ref array)) if !array.is_empty() => value = &array[0],
+            _ => break,
+        }
+    }
+    assert_eq!(levels, depth);
+    drop_nested(json);
+
+    // a failed parse tears down what it built without recursing
+    let source = format!("[{}{}, tru]", r#"{"a":["#.repeat(depth), "]}".repeat(depth));
+    assert!(JsonParser::new(&source).parse().is_err());
+}
+
+#[test]
+fn test_options() {
+    let source = "{ // settings\n unquoted: 'single', \"list\": [+1, .5, 0xff, NaN, /* last */ -Infinity,], }";
+    assert!(JsonParser::new(source).parse().is_err());
+
+    let all = ParserOptions::new().trailing_commas(true).comments(true).single_quotes(true).unquoted_keys(true)
+        .nan_infinity(true).leading_plus(true).leading_decimal_point(true).hex_numbers(true);
+    let json = JsonParser::new(source).with_options(all).parse().unwrap();
+    assert!(JsonParser::new(source).with_options(all).validate().is_ok());
+    match json
+    {
+        JsonValue::JsonObject(ref object) =>
+        {
+            assert_eq!(object["unquoted"], JsonValue::JsonString("single".to_owned()));
+            match object["list"]
+            {
+                JsonValue::JsonArray(ref list) =>
+                {
+                    assert_eq!(list[..3], [JsonValue::JsonNumber(1.0), JsonValue::JsonNumber(0.5), JsonValue::JsonNumber(255.0)]);
+                    assert!(match list[3] {JsonValue::JsonNumber(n) => n.is_nan(), _ => false});
+                    assert_eq!(list[4], JsonValue::JsonNumber(-f64::INFINITY));
+                    assert_eq!(list.len(), 5);
+                },
+                _ => panic!("expected an array"),
+            }
+        },
+        _ => panic!("expected an object"),
+    }
+
+    // each switch enables only its own extension
+    let parses = |source : &str, options : ParserOptions| JsonParser::new(source).with_options(options).parse().is_ok();
+    assert!(parses("[1,]", ParserOptions::new().trailing_commas(true)));
+    assert!(!parses("[1,]", ParserOptions::new().comments(true)));
+    assert!(!parses("[,]", ParserOptions::new().trailing_commas(true)));
+    assert!(parses("{'a':1}", ParserOptions::new().single_quotes(true)));
+    assert!(!parses("{a:1}", ParserOptions::new().single_quotes(true)));
+    assert!(parses("{true:1, NaN:2}", ParserOptions::new().unquoted_keys(true)));
+    assert!(!parses("[NaN]", ParserOptions::new().unquoted_keys(true)));
+    assert!(!parses("[key]", ParserOptions::new().unquoted_keys(true)));
+    assert!(!parses("[0x10]", ParserOptions::new().leading_plus(true)));
+    assert!(!parses("[1] /* unterminated", ParserOptions::new().comments(true)));
+}
+
+#[test]
+fn test_jsonc() {
+    let source = r#"// Editor settings
+{
+    /* font */
+    "editor.fontSize": 14, // points
+    "files.exclude": {
+        // none yet
+    },
+    "list": [1, 2, /* two */],
+}
+"#;
+    assert!(JsonParser::new(source).parse().is_err());
+    let document = jsonc::parse(source).unwrap();
+    assert_eq!(document.value, JsonParser::new(source).with_options(ParserOptions::jsonc()).parse().unwrap());
+
+    let found : Vec<_> = document.comments.iter()
+        .map(|c| (c.body(), c.kind, c.line, c.column, c.path.to_string(), c.placement))
+        .collect();
+    assert_eq!(found, vec![
+        (" Editor settings", CommentKind::Line, 1, 1, "".to_owned(), Placement::Before),
+        (" font ", CommentKind::Block, 3, 5, "/editor.fontSize".to_owned(), Placement::Before),
+        (" points", CommentKind::Line, 4, 28, "/editor.fontSize".to_owned(), Placement::After),
+        (" none yet", CommentKind::Line, 6, 9, "/files.exclude".to_owned(), Placement::Inside),
+        (" two ", CommentKind::Block, 8, 20, "/list/1".to_owned(), Placement::After),
+    ]);
+    assert_eq!(document.comments[2].text, "// points");
+    assert_eq!(&source[document.comments[1].offset..][..10], "/* font */");
+
+    assert!(jsonc::parse("[1] /* unterminated").is_err());
+
+    // written back, every comment is attached to the same value again
+    let comments = |document : &jsonc::JsoncDocument| document.comments.iter()
+        .map(|c| (c.text.clone(), c.path.to_string(), c.placement)).collect::<Vec<_>>();
+    let nested = "/* a */ {\"a\": [ // first\n 1, /* b */ {} /* c */, [/* empty */]], \"b\": {\"c\": null // d\n // e\n}} // end";
+    for source in &[source, nested]
+    {
+        let document = jsonc::parse(source).unwrap();
+        let written = jsonc::to_string(&document).unwrap();
+        let reparsed = jsonc::parse(&written).unwrap();
+        assert_eq!(reparsed.value, document.value, "{}", written);
+        assert_eq!(comments(&reparsed), comments(&document), "{}", written);
+    }
+    assert_eq!(jsonc::to_string(&jsonc::parse(source).unwrap()).unwrap(), r#"// Editor settings
+{
+  /* font */
+  "editor.fontSize": 14, // points
+  "files.exclude": {
+    // none yet
+  },
+  "list": [
+    1,
+    2, /* two */
+  ],
+}
+"#);
+
+    // a comment or extended whitespace may follow a literal directly
+    let jsonc = |source : &str| JsonParser::new(source).with_options(ParserOptions::jsonc()).parse().ok();
+    assert_eq!(jsonc("[null/*x*/]"), Some(JsonValue::JsonArray(vec![JsonValue::Null])));
+    assert_eq!(jsonc("[true// c\n]"), Some(JsonValue::JsonArray(vec![JsonValue::JsonBool(true)])));
+    assert_eq!(jsonc::parse("{\"a\": false/* off */}").unwrap().comments.len(), 1);
+    assert!(JsonParser::new("[null/*x*/]").validate().is_err());
+    assert!(JsonParser::new("[false\u{a0}]").with_options(ParserOptions::new().extended_whitespace(true)).parse().is_ok());
+    assert!(JsonParser::new("[false\u{a0}]").parse().is_err());
+}
+
+#[test]
+fn test_json5() {
+    // values and edge cases the vendored suite (see test_json5_suite) does
+    // not pin down
+    let number = |n : f64| JsonValue::JsonNumber(n);
+    let string = |s : &str| JsonValue::JsonString(s.to_owned());
+    let valid = vec![
+        ("[1,2,]", JsonValue::JsonArray(vec![number(1.0), number(2.0)])),
+        ("[\n  // comment\n  1 /* inline */,\n]", JsonValue::JsonArray(vec![number(1.0)])),
+        ("0x1f", number(31.0)),
+        ("-0XC8", number(-200.0)),
+        (".5", number(0.5)),
+        ("5.", number(5.0)),
+        ("+1.5e2", number(150.0)),
+        ("-Infinity", number(-f64::INFINITY)),
+        ("'single \\' \"quoted\"'", string("single ' \"quoted\"")),
+        ("'line \\\ncontinued'", string("line continued")),
+        ("'line \\\r\ncontinued'", string("line continued")),
+        ("'\\x41\\v\\0\\q\\u00e9'", string("A\x0b\0q\u{e9}")),
+        ("\u{FEFF}\u{a0}\x0b 1 \u{2028}\u{3000}\x0c", number(1.0)),
+        ("'\u{2028}'", string("\u{2028}")),
+    ];
+    for (source, expected) in valid
+    {
+        assert_eq!(json5::parse(source).unwrap(), expected, "{:?}", source);
+    }
+
+    let object = json5::parse("{$_a1: 1, \\u0061b: 2, 'q': 3, \u{e9}t\u{e9}: 4, null: 5, NaN: 6,}").unwrap();
+    let keys = match object
+    {
+        JsonValue::JsonObject(ref object) =>
+        {
+            let mut keys : Vec<_> = object.keys().cloned().collect();
+            keys.sort();
+            keys
+        },
+        _ => panic!("expected an object"),
+    };
+    assert_eq!(keys, vec!["$_a1", "NaN", "ab", "null", "q", "\u{e9}t\u{e9}"]);
+
+    let invalid = ["[,]", "[1,,]", "{a b: 1}", "{1a: 1}", "0x", "01", ".", "+-1", "'\\1'", "'\\xG0'",
+                   "'unterminated", "/* unterminated", "{'a':1,,}", "[1] 2"];
+    for source in invalid.iter()
+    {
+        assert!(json5::parse(source).is_err(), "{:?}", source);
+    }
+
+    // strings are checked alike whether they are decoded or only scanned
+    let escapes = ["'\\1'", "'\\9'", "'\\08'", "'\\x4'", "'\\x4'  ", "'\\x'"];
+    for source in escapes.iter()
+    {
+        let options = ParserOptions::json5();
+        let wrapped = format!("{{\"a\": {}, \"b\": 1}}", source);
+        let deny = || FieldFilter::Deny(vec![PathPattern::parse("/a").unwrap()]);
+        assert!(json5::parse(source).is_err(), "{:?}", source);
+        assert!(JsonParser::new(source).with_options(options).validate().is_err(), "{:?}", source);
+        assert!(SyntaxTree::parse_with_options(source, options).is_err(), "{:?}", source);
+        assert!(JsonParser::new(&wrapped).with_options(options).with_filter(deny()).parse().is_err(), "{:?}", source);
+    }
+    let filtered = JsonParser::new("{'a\\x41': 1, 'b': 2}").with_options(ParserOptions::json5())
+        .with_filter(FieldFilter::Deny(vec![PathPattern::parse("/b").unwrap()])).parse().unwrap();
+    assert!(filtered.get_object_ref().unwrap().contains_key("aA"));
+    for source in ["\"\\a\"", "\"\\1\"", "\"\\'\"", "\"\\x41\""].iter()
+    {
+        assert!(JsonParser::new(source).parse().is_err(), "{:?}", source);
+        assert!(JsonParser::new(source).validate().is_err(), "{:?}", source);
+    }
+    // everything else stays strict JSON by default
+    assert!(JsonParser::new("'a'").parse().is_err());
+}
+
+#[test]
+fn test_json5_suite() {
+    // .json and .json5 files are valid JSON5, .js and .txt files are not
+    let mut dirs = vec![::std::path::PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/json5-tests"))];
+    let mut count = 0;
+    while let Some(dir) = dirs.pop()
+    {
+        for entry in ::std::fs::read_dir(dir).unwrap()
+        {
+            let path = entry.unwrap().path();
+            if path.is_dir()
+            {
+                dirs.push(path);
+                continue;
+            }
+            let source = ::std::fs::read_to_string(&path).unwrap();
+            match path.extension().and_then(|e| e.to_str())
+            {
+                Some("json") | Some("json5") => assert!(json5::parse(&source).is_ok(), "{:?}", path),
+                Some("js") | Some("txt") => assert!(json5::parse(&source).is_err(), "{:?}", path),
+                _ => continue,
+            }
+            count += 1;
+        }
+    }
+    assert!(count > 100, "{}", count);
+}
+
+#[test]
+fn test_json5_write() {
+    let source = r#"{"list": [1.5, -0.25, NaN, -Infinity, [], {}], "it's": 'say "hi"', "\u0000\n": "a\\b"}"#;
+    let value = JsonParser::new(source).with_options(ParserOptions::new().nan_infinity(true).single_quotes(true)).parse().unwrap();
+    let written = json5::to_string(&value);
+    assert!(written.contains("\n  list: [\n    1.5,\n    -0.25,\n    NaN,\n    -Infinity,\n    [],\n    {},\n  ],\n"), "{}", written);
+    assert!(written.contains("\"it's\": 'say \"hi\"',\n"), "{}", written);
+    assert!(written.contains("'\\x00\\n': 'a\\\\b',\n"), "{}", written);
+    assert!(written.starts_with("{\n") && written.ends_with("\n}"));
+
+    // NaN breaks equality, so compare the round trip after writing it again
+    let reparsed = json5::parse(&written).unwrap();
+    assert_eq!(json5::to_string(&reparsed).len(), written.len());
+    assert_eq!(json5::to_string(&JsonValue::JsonArray(vec![])), "[]");
+    assert_eq!(json5::to_string(&JsonValue::JsonNumber(1e300)), "1e300");
+    assert_eq!(json5::to_string(&JsonValue::JsonNumber(-1.5e-7)), "-1.5e-7");
+    assert_eq!(json5::to_string(&JsonValue::JsonString("it's".to_owned())), "\"it's\"");
+}
+
+#[test]
+fn test_ijson() {
+    let source = r#"{"a": 1, "b": [0.1, 3.141592653589793238, 9007199254740993, 1e400],
+                     "c": "\ud800 \udc00 😀 ￿", "a": {"﷐": null}}"#;
+    let violations = ijson::check_source(source).unwrap();
+    let found : Vec<_> = violations.iter().map(|v| (v.path.to_string(), v.kind.clone())).collect();
+    assert_eq!(found, vec![
+        ("/b/1".to_owned(), ViolationKind::ImpreciseNumber("3.141592653589793238".to_owned())),
+        ("/b/2".to_owned(), ViolationKind::ImpreciseNumber("9007199254740993".to_owned())),
+        ("/b/3".to_owned(), ViolationKind::ImpreciseNumber("1e400".to_owned())),
+        ("/c".to_owned(), ViolationKind::LoneSurrogate(0xD800)),
+        ("/c".to_owned(), ViolationKind::LoneSurrogate(0xDC00)),
+        ("/c".to_owned(), ViolationKind::Noncharacter('\u{FFFF}')),
+        ("/a".to_owned(), ViolationKind::DuplicateKey("a".to_owned())),
+        ("/a/\u{FDD0}".to_owned(), ViolationKind::Noncharacter('\u{FDD0}')),
+    ]);
+    assert_eq!(violations[6].offset, Some(source.rfind("\"a\"").unwrap()));
+    assert_eq!(violations[0].to_string(), "number 3.141592653589793238 is not representable as a double at \"/b/1\" (byte 20)");
+
+    assert_eq!(ijson::check_source(r#"[9007199254740992, 1.5e300, 0.0, -0, 12e-1]"#).unwrap(), vec![
+        ijson::Violation{path : JsonPointer::parse("/0").unwrap(), kind : ViolationKind::IntegerOutOfRange(9007199254740992.0), offset : Some(1)},
+        ijson::Violation{path : JsonPointer::parse("/1").unwrap(), kind : ViolationKind::IntegerOutOfRange(1.5e300), offset : Some(19)},
+    ]);
+    assert!(ijson::check_source("[1,]").is_err());
+
+    let value = JsonParser::new(r#"{"n": [1, 10000000000000000, "￿"], "ok": 9007199254740991}"#).parse().unwrap();
+    let found : Vec<_> = ijson::check_value(&value).into_iter().map(|v| (v.path.to_string(), v.kind)).collect();
+    assert_eq!(found, vec![
+        ("/n/1".to_owned(), ViolationKind::IntegerOutOfRange(1e16)),
+        ("/n/2".to_owned(), ViolationKind::Noncharacter('\u{FFFF}')),
+    ]);
+    assert_eq!(ijson::check_value(&JsonValue::JsonNumber(f64::NAN)).len(), 1);
+
+    // raw values are written as they are, so their text is checked
+    let mut object = JsonObject::new();
+    object.insert("dup".to_owned(), JsonValue::JsonRaw(RawValue::new("{\"a\":1,\"a\":2}").unwrap()));
+    object.insert("big".to_owned(), JsonValue::JsonRaw(RawValue::new("9007199254740993").unwrap()));
+    let found : Vec<_> = ijson::check_value(&JsonValue::JsonObject(object)).into_iter().map(|v| (v.path.to_string(), v.kind)).collect();
+    assert_eq!(found, vec![
+        ("/dup/a".to_owned(), ViolationKind::DuplicateKey("a".to_owned())),
+        ("/big".to_owned(), ViolationKind::ImpreciseNumber("9007199254740993".to_owned())),
+    ]);
+}
+
+#[test]
+fn test_duplicate_keys() {
+    let source = r#"{"a": 1, "b": [{"c": true, "c": false}], "a": 2, "a": 3}"#;
+    let parse = |policy| JsonParser::new(source).with_options(ParserOptions::new().duplicate_keys(policy)).parse();
+    let value_of = |value : &JsonValue, key : &str| match *value
+    {
+        JsonValue::JsonObject(ref object) => object[key].clone(),
+        _ => panic!("expected an object"),
+    };
+
+    let last = parse(DuplicateKeys::LastWins).unwrap();
+    assert_eq!(last, JsonParser::new(source).parse().unwrap());
+    assert_eq!(value_of(&last, "a"), JsonValue::JsonNumber(3.0));
+    let first = parse(DuplicateKeys::FirstWins).unwrap();
+    assert_eq!(value_of(&first, "a"), JsonValue::JsonNumber(1.0));
+    let inner = match value_of(&first, "b") {JsonValue::JsonArray(ref array) => array[0].clone(), _ => panic!("expected an array")};
+    assert_eq!(value_of(&inner, "c"), JsonValue::JsonBool(true));
+
+    let error = parse(DuplicateKeys::Error).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::DuplicateKey{first : 16, second : 27});
+    assert_eq!(error.span(), r#""c""#);
+    let validated = JsonParser::new(source).with_options(ParserOptions::new().duplicate_keys(DuplicateKeys::Error)).validate();
+    assert_eq!(validated.unwrap_err().kind(), ErrorKind::DuplicateKey{first : 16, second : 27});
+    assert!(JsonParser::new(r#"{"a": {"a": 1}, "b": {"a": 2}}"#)
+        .with_options(ParserOptions::new().duplicate_keys(DuplicateKeys::Error)).parse().is_ok());
+    // members the filter drops are checked as validate checks them
+    for &(source, first, second) in &[(r#"{"a": 1, "b": 2, "a": 3}"#, 1, 17), (r#"{"b": 1, "a": 2, "b": 3}"#, 1, 17)]
+    {
+        let options = ParserOptions::new().duplicate_keys(DuplicateKeys::Error);
+        let drop_a = FieldFilter::Deny(vec![PathPattern::parse("/a").unwrap()]);
+        let error = JsonParser::new(source).with_options(options).with_filter(drop_a).parse().unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::DuplicateKey{first, second});
+        assert_eq!(JsonParser::new(source).with_options(options).validate().unwrap_err().kind(), error.kind());
+    }
+
+    let mut parser = JsonParser::new(source).with_options(ParserOptions::new().duplicate_keys(DuplicateKeys::KeepAll));
+    assert_eq!(parser.parse().unwrap(), last);
+    let duplicates : Vec<_> = parser.duplicates().iter().map(|d| (d.path.to_string(), d.values.clone())).collect();
+    assert_eq!(duplicates, vec![
+        ("/b/0/c".to_owned(), vec![(16, JsonValue::JsonBool(true)), (27, JsonValue::JsonBool(false))]),
+        ("/a".to_owned(), vec![(1, JsonValue::JsonNumber(1.0)), (41, JsonValue::JsonNumber(2.0)), (49, JsonValue::JsonNumber(3.0))]),
+    ]);
+}
+
+#[test]
+fn test_member_order() {
+    let source = r#"{"zeta": 1, "alpha": {"y": true, "x": false}, "mid": null, "alpha": 2}"#;
+    let mut json = JsonParser::new(source).parse().unwrap();
+    let object = match json
+    {
+        JsonValue::JsonObject(ref mut object) => object,
+        _ => panic!("expected an object"),
+    };
+    // a repeated key keeps the position of its first occurrence
+    assert_eq!(object.keys().collect::<Vec<_>>(), ["zeta", "alpha", "mid"]);
+    assert_eq!(object["alpha"], JsonValue::JsonNumber(2.0));
+    assert_eq!(object.position("mid"), Some(2));
+
+    let mut reordered : JsonObject = object.iter().rev().map(|(k, v)| (k.clone(), v.clone())).collect();
+    assert_eq!(&reordered, &*object);
+    reordered.sort_keys();
+    assert_eq!(reordered.keys().collect::<Vec<_>>(), ["alpha", "mid", "zeta"]);
+
+    assert_eq!(object.remove("zeta"), Some(JsonValue::JsonNumber(1.0)));
+    object.insert("zeta".to_owned(), JsonValue::Null);
+    object.retain(|key, _| key != "mid");
+    assert_eq!(object.iter().map(|(k, _)| k.as_str()).collect::<Vec<_>>(), ["alpha", "zeta"]);
+    assert_eq!(object.get("zeta"), Some(&JsonValue::Null));
+    assert!(!object.contains_key("mid"));
+
+    // removals keep every later position in the index right
+    let mut map : JsonObject = (0..6).map(|i| (i.to_string(), JsonValue::JsonNumber(f64::from(i)))).collect();
+    map.retain(|key, _| key != "1" && key != "2" && key != "4");
+    assert_eq!(map.keys().collect::<Vec<_>>(), ["0", "3", "5"]);
+    assert_eq!((map.position("3"), map.position("5"), map.get("4")), (Some(1), Some(2), None));
+    map.remove("0");
+    assert_eq!((map.position("3"), map.position("5")), (Some(0), Some(1)));
+    if let Entry::Occupied(entry) = map.entry("3".to_owned())
+    {
+        entry.remove();
+    }
+    assert_eq!((map.position("5"), map.len()), (Some(0), 1));
+
+    let nested = JsonParser::new(r#"{"b": {"y": 1, "x": 2}, "a": []}"#).parse().unwrap();
+    assert_eq!(json5::to_string(&nested), "{\n  b: {\n    y: 1,\n    x: 2,\n  },\n  a: [],\n}");
+}
+
+#[test]
+fn test_syntax_tree() {
+    let source = "{\n  // the name\n  \"name\" : \"caf\\u00e9\",\n  \"sizes\": [1.50, 2e3],\n  \"flags\": {}\n}\n";
+    let path = |p : &str| JsonPointer::parse(p).unwrap();
+    let mut tree = SyntaxTree::parse_with_options(source, ParserOptions::jsonc()).unwrap();
+    assert_eq!(tree.to_string(), source);
+    assert_eq!(tree.get(&path("/name")), Some("\"caf\\u00e9\""));
+    assert_eq!(tree.get(&path("/sizes/0")), Some("1.50"));
+    assert_eq!(tree.node(&path("/sizes")).unwrap().kind(), NodeKind::Array);
+    assert_eq!(tree.root().len(), 3);
+
+    tree.set(&path("/sizes/1"), " 7 ").unwrap();
+    assert_eq!(tree.text(), source.replace("2e3", "7"));
+
+    tree.insert(&path("/sizes/-"), "8").unwrap();
+    tree.insert(&path("/sizes/0"), "0.5").unwrap();
+    tree.insert(&path("/flags/on"), "true").unwrap();
+    tree.insert(&path("/extra"), "null").unwrap();
+    assert_eq!(tree.text(), "{\n  // the name\n  \"name\" : \"caf\\u00e9\",\n  \"sizes\": [0.5, 1.50, 7, 8],\n  \"flags\": {\"on\": true},\n  \"extra\": null\n}\n");
+
+    tree.remove(&path("/sizes/1")).unwrap();
+    tree.remove(&path("/sizes/2")).unwrap();
+    tree.remove(&path("/flags/on")).unwrap();
+    tree.remove(&path("/extra")).unwrap();
+    assert_eq!(tree.text(), "{\n  // the name\n  \"name\" : \"caf\\u00e9\",\n  \"sizes\": [0.5, 7],\n  \"flags\": {}\n}\n");
+
+    assert_eq!(tree.set(&path("/missing"), "1"), Err(EditError::NotFound(path("/missing"))));
+    assert_eq!(tree.insert(&path("/name"), "1"), Err(EditError::AlreadyExists(path("/name"))));
+    assert_eq!(tree.insert(&path("/name/x"), "1"), Err(EditError::NoContainer(path("/name/x"))));
+    assert!(tree.set(&path("/name"), "[1,").is_err());
+    assert!(SyntaxTree::parse("[1,]").is_err());
+
+    // trailing commas and layout of the neighbours are kept
+    let mut tree = SyntaxTree::parse_with_options("[\n    1,\n    2,\n]", ParserOptions::jsonc()).unwrap();
+    tree.insert(&path("/-"), "3").unwrap();
+    assert_eq!(tree.text(), "[\n    1,\n    2,\n    3,\n]");
+    tree.remove(&path("/2")).unwrap();
+    tree.remove(&path("/0")).unwrap();
+    assert_eq!(tree.text(), "[\n    2,\n]");
+    tree.remove(&path("/0")).unwrap();
+    assert_eq!(tree.text(), "[\n]");
+}
+
+#[test]
+fn test_source_map() {
+    let source = "{\n  \"name\": \"é\",\n  \"list\": [1, {\"x\": null}],\n  \"name\": true\n}";
+    let path = |p : &str| JsonPointer::parse(p).unwrap();
+    let (value, map) = JsonParser::new(source).parse_with_spans().unwrap();
+    assert_eq!(value, JsonParser::new(source).parse().unwrap());
+    assert_eq!(map.len(), 7);
+
+    let root = map.value_span(&path("")).unwrap();
+    assert_eq!((root.start.offset, root.end.offset), (0, source.len()));
+    assert_eq!((root.end.line, root.end.column), (5, 2));
+
+    // a repeated key maps to the member that wins
+    let name = map.value_span(&path("/name")).unwrap();
+    assert_eq!(&source[name.start.offset..name.end.offset], "true");
+    let key = map.key_span(&path("/name")).unwrap();
+    assert_eq!((key.start.line, key.start.column, key.end.column), (4, 3, 9));
+
+    let x = map.value_span(&path("/list/1/x")).unwrap();
+    assert_eq!((x.start.line, x.start.column), (3, 21));
+    assert_eq!(map.key_span(&path("/list/1")), None);
+
+    assert_eq!(map.path_at(x.start.offset), Some(&path("/list/1/x")));
+    assert_eq!(map.path_at(x.start.offset - 4), Some(&path("/list/1/x")));
+    assert_eq!(map.path_at(x.end.offset), Some(&path("/list/1")));
+    assert_eq!(map.path_at(source.find("1,").unwrap()), Some(&path("/list/0")));
+    assert_eq!(map.path_at(source.find("\"list\"").unwrap() - 1), Some(&path("")));
+    assert_eq!(map.path_at(source.len()), None);
+
+    let (_, map) = JsonParser::new(source).with_options(ParserOptions::new().duplicate_keys(DuplicateKeys::FirstWins)).parse_with_spans().unwrap();
+    assert_eq!(map.value_span(&path("/name")).unwrap().start.column, 11);
+
+    // the map follows the value the parser built
+    let source = r#"{"a": {"x": 1, "y": 2}, "b": [3], "a": {"z": 4}}"#;
+    let (value, map) = JsonParser::new(source).parse_with_spans().unwrap();
+    assert!(value.get_object_ref().unwrap().get_object_ref("a").unwrap().unwrap().contains_key("z"));
+    assert_eq!(map.value_span(&path("/a")).unwrap().start.offset, source.rfind('{').unwrap());
+    assert_eq!((map.entry(&path("/a/x")), map.entry(&path("/a/z")).is_some()), (None, true));
+    let options = ParserOptions::new().duplicate_keys(DuplicateKeys::FirstWins);
+    let (_, map) = JsonParser::new(source).with_options(options).parse_with_spans().unwrap();
+    assert_eq!((map.entry(&path("/a/x")).is_some(), map.entry(&path("/a/z"))), (true, None));
+
+    let patterns = vec![PathPattern::parse("/b").unwrap()];
+    let (value, map) = JsonParser::new(source).with_filter(FieldFilter::Deny(patterns)).parse_with_spans().unwrap();
+    assert!(!value.get_object_ref().unwrap().contains_key("b"));
+    assert_eq!((map.entry(&path("/b")), map.entry(&path("/b/0")), map.len()), (None, None, 6));
+
+    let capture = RawCapture::Fields(vec!["b".to_owned()]);
+    let (_, map) = JsonParser::new(source).with_raw_capture(capture).parse_with_spans().unwrap();
+    let b = map.value_span(&path("/b")).unwrap();
+    assert_eq!((&source[b.start.offset..b.end.offset], map.entry(&path("/b/0"))), ("[3]", None));
+}
+
+#[test]
+fn test_incremental_edit() {
+    let source = "{\"a\": [1, 2, {\"b\": \"x\"}], \"c\": 10}";
+    let path = |p : &str| JsonPointer::parse(p).unwrap();
+    let mut tree = SyntaxTree::parse(source).unwrap();
+
+    // typing inside a number re-parses only that number
+    let (start, end) = tree.node(&path("/a/1")).unwrap().span();
+    assert_eq!(tree.apply_edit(end, end, "34"), Ok((start, end + 2)));
+    assert_eq!(tree.get(&path("/a/1")), Some("234"));
+    assert_eq!(tree.get(&path("/a/2/b")), Some("\"x\""));
+    assert_eq!(tree.get(&path("/c")), Some("10"));
+    assert_eq!(tree.node(&path("/c")).unwrap().span(), (source.len() - 1, source.len() + 1));
+
+    // a comma inside an array changes the array, not the element
+    let (start, end) = tree.node(&path("/a")).unwrap().span();
+    let comma = tree.text().find("234").unwrap() + 3;
+    assert_eq!(tree.apply_edit(comma, comma, ", 5"), Ok((start, end + 3)));
+    assert_eq!(tree.get(&path("/a/2")), Some("5"));
+    assert_eq!(tree.get(&path("/a/3/b")), Some("\"x\""));
+    assert_eq!(tree.root(), SyntaxTree::parse(tree.text()).unwrap().root());
+
+    // an edit of a key reaches the whole object
+    let key = tree.text().find("\"c\"").unwrap() + 1;
+    tree.apply_edit(key, key + 1, "d").unwrap();
+    assert_eq!(tree.get(&path("/d")), Some("10"));
+    assert_eq!(tree.get(&path("/c")), None);
+
+    // edits that break the document are refused and change nothing
+    let before = tree.text().to_owned();
+    assert!(match tree.apply_edit(1, 1, "[") {Err(EditError::InvalidText(_)) => true, _ => false});
+    assert_eq!(tree.apply_edit(5, 2, ""), Err(EditError::InvalidRange(5, 2)));
+    assert_eq!(tree.text(), before);
+
+    // whitespace around the root needs the whole document
+    let end = tree.text().len();
+    assert_eq!(tree.apply_edit(end, end, "\n"), Ok((0, end + 1)));
+    assert_eq!(tree.root().span(), (0, end));
+    assert_eq!(tree.to_string(), "{\"a\": [1, 234, 5, {\"b\": \"x\"}], \"d\": 10}\n");
+    assert_eq!(tree.root(), SyntaxTree::parse(tree.text()).unwrap().root());
+}
+
+#[test]
+fn test_raw_values() {
+    let source = r#"{"id": 1.50, "payload": {"n": 1E+2, "s": "café"}, "items": [{"payload": [ 0.10 ]}, 2.0]}"#;
+    let mut parser = JsonParser::new(source).with_raw_capture(RawCapture::Fields(vec!["payload".to_owned()]));
+    let json = parser.parse().unwrap();
+    let object = json.get_object_ref().unwrap();
+    assert_eq!(object.get_number("id").unwrap(), Some(1.5));
+
+    let raw = match json
+    {
+        JsonValue::JsonObject(ref object) => object["payload"].get_raw_ref().unwrap().clone(),
+        _ => panic!("expected an object"),
+    };
+    assert_eq!(raw.as_str(), r#"{"n": 1E+2, "s": "café"}"#);
+    assert_eq!(raw.parse().unwrap(), JsonParser::new(r#"{"n": 100, "s": "café"}"#).parse().unwrap());
+    assert_eq!(json5::to_string(&json), "{\n  id: 1.5,\n  payload: {\"n\": 1E+2, \"s\": \"café\"},\n  items: [\n    {\n      payload: [ 0.10 ],\n    },\n    2,\n  ],\n}");
+
+    let mut parser = JsonParser::new(source).with_raw_capture(RawCapture::Paths(vec![PathPattern::parse("/items/*").unwrap()]));
+    let json = parser.parse().unwrap();
+    let items = json.get_object_ref().unwrap();
+    let items = items.get_array_ref("items").unwrap().unwrap();
+    assert!(items.get_object_ref(0).is_err());
+    let json = JsonParser::new(source).with_raw_capture(RawCapture::Paths(vec![PathPattern::parse("/items/1").unwrap()])).parse().unwrap();
+    let expected = JsonParser::new("{\"payload\": [0.1]}").parse().unwrap();
+    match json
+    {
+        JsonValue::JsonObject(ref object) => match object["items"]
+        {
+            JsonValue::JsonArray(ref items) =>
+            {
+                assert_eq!(items[0], expected);
+                assert_eq!(items[1], JsonValue::JsonRaw(RawValue::new("2.0").unwrap()));
+            },
+            _ => panic!("expected an array"),
+        },
+        _ => panic!("expected an object"),
+    }
+
+    // the raw text is still checked
+    assert!(JsonParser::new(r#"{"payload": [1,]}"#).with_raw_capture(RawCapture::Fields(vec!["payload".to_owned()])).parse().is_err());
+    assert!(RawValue::new("[1 2]").is_err());
+    assert_eq!(RawValue::new(" true ").unwrap().to_string(), "true");
+
+    // only strict raw text is written verbatim, and only in compact output
+    let capture = || RawCapture::Fields(vec!["a".to_owned(), "b".to_owned()]);
+    let source = "{\"a\": {\"x\": [1, /* one */ 2,], 'y': 0x10}, \"b\": [ 1.50 ]}";
+    let json = JsonParser::new(source).with_options(ParserOptions::json5()).with_raw_capture(capture()).parse().unwrap();
+    assert_eq!(json.to_json().unwrap(), r#"{"a":{"x":[1,2],"y":16},"b":[1.5]}"#);
+    let source = r#"{"a": {"x": [1, 2], "y": 16}, "b": [ 1.50 ]}"#;
+    let json = JsonParser::new(source).with_raw_capture(capture()).parse().unwrap();
+    let parsed = JsonParser::new(source).parse().unwrap();
+    assert_eq!(json.to_json().unwrap(), r#"{"a":{"x": [1, 2], "y": 16},"b":[ 1.50 ]}"#);
+    for config in &[PrettyConfig::new(), PrettyConfig::new().tabs().max_width(24)]
+    {
+        assert_eq!(json.to_json_pretty(config.clone()).unwrap(), parsed.to_json_pretty(config.clone()).unwrap());
+    }
+}
+
+#[test]
+fn test_serialize() {
+    let source = "{\"name\": \"tab\\t \\\"quoted\\\" \\\\ \\u0001 \\ud83d\\ude00 é\", \"list\": [1.0, -0.5, 1e21, true, null, {}, []], \"nested\": {\"a\": [[]]}}";
+    let json = JsonParser::new(source).parse().unwrap();
+    let text = json.to_json().unwrap();
+    assert_eq!(text, "{\"name\":\"tab\\t \\\"quoted\\\" \\\\ \\u0001 😀 é\",\"list\":[1,-0.5,1e21,true,null,{},[]],\"nested\":{\"a\":[[]]}}");
+    assert_eq!(JsonParser::new(&text).parse().unwrap(), json);
+    assert_eq!(json.to_string(), text);
+
+    let mut bytes = Vec::new();
+    json.write_json_to(&mut bytes).unwrap();
+    assert_eq!(String::from_utf8(bytes).unwrap(), text);
+
+    let object = match json
+    {
+        JsonValue::JsonObject(ref object) => object,
+        _ => panic!("expected an object"),
+    };
+    assert_eq!(object.to_string(), text);
+    let array : JsonArray = vec![JsonValue::JsonString("\u{7f}\u{1f}".to_owned()), JsonValue::JsonNumber(f64::NAN)];
+    match array.to_json()
+    {
+        Err(SerializeError::NonFiniteNumber(n)) => assert!(n.is_nan()),
+        result => panic!("unexpected {:?}", result),
+    }
+    assert_eq!(JsonValue::JsonArray(array).to_string(), "[\"\u{7f}\\u001f\",null]");
+
+    for &number in &[0.1 + 0.2, 1.2345678901234567e-300, 12345678901234567890.0, 5e-324, f64::MAX, -1e-7, 123.456]
+    {
+        let text = JsonValue::JsonNumber(number).to_json().unwrap();
+        assert_eq!(JsonParser::new(&text).parse().unwrap(), JsonValue::JsonNumber(number));
+    }
+
+    // deep nesting is written without recursion
+    let deep = "[".repeat(100_000) + &"]".repeat(100_000);
+    let json = JsonParser::new(&deep).parse().unwrap();
+    assert_eq!(json.to_json().unwrap(), deep);
+    drop_nested(json);
+}
+
+#[test]
+fn test_pretty() {
+    let json = JsonParser::new(r#"{"b": [1, 2, [3]], "a": {"x": "y", "e": {}}, "c": []}"#).parse().unwrap();
+    assert_eq!(json.to_json_pretty(PrettyConfig::new()).unwrap(),
+               "{\n  \"b\": [\n    1,\n    2,\n    [\n      3\n    ]\n  ],\n  \"a\": {\n    \"x\": \"y\",\n    \"e\": {}\n  },\n  \"c\": []\n}");
+
+    let config = PrettyConfig::new().tabs().sort_keys(true).space_after_colon(false).line_ending(LineEnding::CrLf).final_newline(true);
+    assert_eq!(json.to_json_pretty(config).unwrap(),
+               "{\r\n\t\"a\":{\r\n\t\t\"e\":{},\r\n\t\t\"x\":\"y\"\r\n\t},\r\n\t\"b\":[\r\n\t\t1,\r\n\t\t2,\r\n\t\t[\r\n\t\t\t3\r\n\t\t]\r\n\t],\r\n\t\"c\":[]\r\n}\r\n");
+
+    // containers stay on one line when they fit
+    let config = PrettyConfig::new().indent_width(4).max_width(20);
+    assert_eq!(json.to_json_pretty(config).unwrap(),
+               "{\n    \"b\": [1, 2, [3]],\n    \"a\": {\n        \"x\": \"y\",\n        \"e\": {}\n    },\n    \"c\": []\n}");
+    assert_eq!(json.to_json_pretty(PrettyConfig::new().max_width(80)).unwrap(),
+               "{\"b\": [1, 2, [3]], \"a\": {\"x\": \"y\", \"e\": {}}, \"c\": []}");
+
+    let object = match json
+    {
+        JsonValue::JsonObject(ref object) => object,
+        _ => panic!("expected an object"),
+    };
+    let options = SerializerOptions::new().pretty(PrettyConfig::new().sort_keys(true).max_width(30));
+    let text = object.to_json_with(&options).unwrap();
+    assert_eq!(text, "{\n  \"a\": {\"e\": {}, \"x\": \"y\"},\n  \"b\": [1, 2, [3]],\n  \"c\": []\n}");
+    assert_eq!(JsonParser::new(&text).parse().unwrap(), json);
+}
+
+#[test]
+fn test_json_writer() {
+    let nested = JsonParser::new(r#"{"x": [1, 2]}"#).parse().unwrap();
+    let write = |writer : &mut JsonWriter<Vec<u8>>| -> ::std::result::Result<(), SerializeError>
+    {
+        writer.begin_object()?;
+        writer.key("name")?;
+        writer.string("a\"b")?;
+        writer.key("items")?;
+        writer.begin_array()?;
+        writer.value(&nested)?;
+        writer.raw(" 1.50 ")?;
+        writer.begin_array()?;
+        writer.end_array()?;
+        writer.end_array()?;
+        writer.key("empty")?;
+        writer.begin_object()?;
+        writer.end_object()?;
+        writer.end_object()
+    };
+
+    let mut writer = JsonWriter::new(Vec::new());
+    write(&mut writer).unwrap();
+    let text = String::from_utf8(writer.finish().unwrap()).unwrap();
+    assert_eq!(text, r#"{"name":"a\"b","items":[{"x":[1,2]},1.50,[]],"empty":{}}"#);
+
+    let options = SerializerOptions::new().pretty(PrettyConfig::new().max_width(30).final_newline(true));
+    let mut writer = JsonWriter::new(Vec::new()).with_options(options);
+    write(&mut writer).unwrap();
+    let text = String::from_utf8(writer.finish().unwrap()).unwrap();
+    assert_eq!(text, "{\n  \"name\": \"a\\\"b\",\n  \"items\": [\n    {\"x\": [1, 2]},\n    1.50,\n    []\n  ],\n  \"empty\": {}\n}\n");
+
+    let structure = |result : ::std::result::Result<(), SerializeError>| match result
+    {
+        Err(SerializeError::InvalidStructure(_)) => true,
+        _ => false,
+    };
+    let mut writer = JsonWriter::new(Vec::new());
+    assert!(structure(writer.key("a")));
+    assert!(structure(writer.end_array()));
+    writer.begin_object().unwrap();
+    assert!(structure(writer.string("no key")));
+    assert!(structure(writer.end_array()));
+    writer.key("a").unwrap();
+    assert!(structure(writer.key("b")));
+    assert!(structure(writer.end_object()));
+    match writer.raw("[1,")
+    {
+        Err(SerializeError::InvalidFragment(_)) => {},
+        result => panic!("unexpected {:?}", result),
+    }
+    writer.value(&JsonValue::Null).unwrap();
+    writer.end_object().unwrap();
+    assert!(structure(writer.begin_array()));
+    assert_eq!(writer.finish().unwrap(), b"{\"a\":null}".to_vec());
+
+    let mut writer = JsonWriter::new(Vec::new());
+    writer.begin_array().unwrap();
+    assert!(match writer.finish() {Err(SerializeError::InvalidStructure(_)) => true, _ => false});
+}
+
+#[test]
+fn test_escaping() {
+    let json = JsonValue::JsonString("<a href='x'>&</a> é \u{2028}\u{2029} 😀 \u{1}".to_owned());
+    assert_eq!(json.to_json().unwrap(), "\"<a href='x'>&</a> é \u{2028}\u{2029} 😀 \\u0001\"");
+
+    let ascii = SerializerOptions::new().escaping(Escaping::minimal().ascii_only(true));
+    let text = json.to_json_with(&ascii).unwrap();
+    assert_eq!(text, "\"<a href='x'>&</a> \\u00e9 \\u2028\\u2029 \\ud83d\\ude00 \\u0001\"");
+    assert!(text.is_ascii());
+    assert_eq!(JsonParser::new(&text).parse().unwrap(), json);
+
+    let html = SerializerOptions::new().escaping(Escaping::minimal().html_safe(true));
+    let text = json.to_json_with(&html).unwrap();
+    assert_eq!(text, "\"\\u003ca href=\\u0027x\\u0027\\u003e\\u0026\\u003c/a\\u003e é \u{2028}\u{2029} 😀 \\u0001\"");
+    assert_eq!(JsonParser::new(&text).parse().unwrap(), json);
+
+    let js = SerializerOptions::new().escaping(Escaping::minimal().js_safe(true));
+    assert_eq!(json.to_json_with(&js).unwrap(), "\"<a href='x'>&</a> é \\u2028\\u2029 😀 \\u0001\"");
+
+    // keys are escaped alike, by the streaming writer too
+    let all = SerializerOptions::new().escaping(Escaping::minimal().ascii_only(true).html_safe(true).js_safe(true));
+    let mut writer = JsonWriter::new(Vec::new()).with_options(all);
+    writer.begin_object().unwrap();
+    writer.key("</script>").unwrap();
+    writer.string("ü").unwrap();
+    writer.end_object().unwrap();
+    assert_eq!(String::from_utf8(writer.finish().unwrap()).unwrap(), "{\"\\u003c/script\\u003e\":\"\\u00fc\"}");
+
+    // and so are raw values
+    let source = r#"{"a": "</script><x>", "b": "é"}"#;
+    let capture = RawCapture::Fields(vec!["a".to_owned(), "b".to_owned()]);
+    let json = JsonParser::new(source).with_raw_capture(capture).parse().unwrap();
+    let safe = SerializerOptions::new().escaping(Escaping::minimal().html_safe(true).ascii_only(true));
+    assert_eq!(json.to_json_with(&safe).unwrap(), r#"{"a":"\u003c/script\u003e\u003cx\u003e","b":"\u00e9"}"#);
+    assert_eq!(json.to_json().unwrap(), r#"{"a":"</script><x>","b":"é"}"#);
+    let mut writer = JsonWriter::new(Vec::new()).with_options(safe);
+    writer.raw(r#"["</script>", "é"]"#).unwrap();
+    assert_eq!(String::from_utf8(writer.finish().unwrap()).unwrap(), r#"["\u003c/script\u003e","\u00e9"]"#);
+}
+
+#[test]
+fn test_number_format() {
+    let numbers = |format : NumberFormat, values : &[f64]| {
+        let array : JsonArray = values.iter().map(|&n| JsonValue::JsonNumber(n)).collect();
+        array.to_json_with(&SerializerOptions::new().number_format(format)).unwrap()
+    };
+    let values = [1.0, -0.0, 0.1 + 0.2, 1.5e-6, 5e-7, 123456789012345680000.0, 1e21, -2.5e300, 5e-324];
+    assert_eq!(numbers(NumberFormat::Shortest, &values),
+               "[1,-0,0.30000000000000004,0.0000015,5e-7,123456789012345680000,1e21,-2.5e300,5e-324]");
+    assert_eq!(numbers(NumberFormat::Fixed(2), &[1.0, 0.125, -7.26549, 1e3]), "[1.00,0.12,-7.27,1000.00]");
+    assert_eq!(numbers(NumberFormat::Exponent, &[1.0, 1500.0, 0.25, -1e-9]), "[1e0,1.5e3,2.5e-1,-1e-9]");
+
+    for &number in &[0.1, 1e-7, 9.999999999999999e20, 1.7976931348623157e308, 2.2250738585072014e-308, -123.456e-50]
+    {
+        let text = numbers(NumberFormat::Shortest, &[number]);
+        assert_eq!(JsonParser::new(&text).parse().unwrap(), JsonValue::JsonArray(vec![JsonValue::JsonNumber(number)]));
+    }
+
+    let mut json = JsonParser::new("{\"a\": 1, \"b\": [2, 3]}").parse().unwrap();
+    if let JsonValue::JsonObject(ref mut object) = json
+    {
+        object.insert("c".to_owned(), JsonValue::JsonNumber(f64::INFINITY));
+        object.insert("d".to_owned(), JsonValue::JsonNumber(f64::NEG_INFINITY));
+        object.insert("e".to_owned(), JsonValue::JsonNumber(f64::NAN));
+    }
+    match json.to_json()
+    {
+        Err(SerializeError::NonFiniteNumber(n)) => assert_eq!(n, f64::INFINITY),
+        result => panic!("unexpected {:?}", result),
+    }
+    let null = SerializerOptions::new().non_finite(NonFinite::Null);
+    assert_eq!(json.to_json_with(&null).unwrap(), "{\"a\":1,\"b\":[2,3],\"c\":null,\"d\":null,\"e\":null}");
+    let string = SerializerOptions::new().non_finite(NonFinite::String);
+    assert_eq!(json.to_json_with(&string).unwrap(), "{\"a\":1,\"b\":[2,3],\"c\":\"Infinity\",\"d\":\"-Infinity\",\"e\":\"NaN\"}");
+}
+
+#[test]
+fn test_jcs() {
+    // RFC 8785, section 3.2.2
+    let source = r#"{
+      "numbers": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001],
+      "string": "\u20ac$\u000F\u000aA'\u0042\u0022\u005c\\\"\/",
+      "literals": [null, true, false]
+    }"#;
+    let json = JsonParser::new(source).parse().unwrap();
+    assert_eq!(jcs::to_string(&json).unwrap(),
+               r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#);
+
+    // RFC 8785, section 3.2.3: sorting by UTF-16 code units
+    let source = r#"{
+      "€": "Euro Sign",
+      "\r": "Carriage Return",
+      "דּ": "Hebrew Letter Dalet With Dagesh",
+      "1": "One",
+      "😀": "Emoji: Grinning Face",
+      "\u0080": "Control",
+      "ö": "Latin Small Letter O With Diaeresis"
+    }"#;
+    let json = JsonParser::new(source).parse().unwrap();
+    let keys : Vec<String> = match &JsonParser::new(&jcs::to_string(&json).unwrap()).parse().unwrap()
+    {
+        JsonValue::JsonObject(ref object) => object.keys().cloned().collect(),
+        other => panic!("expected an object, got {:?}", other),
+    };
+    assert_eq!(keys, ["\r", "1", "\u{80}", "ö", "€", "😀", "\u{fb33}"]);
+
+    // RFC 8785, appendix B
+    let vectors : [(u64, &str); 24] = [
+        (0x0000000000000000, "0"), (0x8000000000000000, "0"),
+        (0x0000000000000001, "5e-324"), (0x8000000000000001, "-5e-324"),
+        (0x7fefffffffffffff, "1.7976931348623157e+308"), (0xffefffffffffffff, "-1.7976931348623157e+308"),
+        (0x4340000000000000, "9007199254740992"), (0xc340000000000000, "-9007199254740992"),
+        (0x4430000000000000, "295147905179352830000"), (0x44b52d02c7e14af5, "9.999999999999997e+22"),
+        (0x44b52d02c7e14af6, "1e+23"), (0x44b52d02c7e14af7, "1.0000000000000001e+23"),
+        (0x444b1ae4d6e2ef4e, "999999999999999700000"), (0x444b1ae4d6e2ef4f, "999999999999999900000"),
+        (0x444b1ae4d6e2ef50, "1e+21"), (0x3eb0c6f7a0b5ed8c, "9.999999999999997e-7"),
+        (0x3eb0c6f7a0b5ed8d, "0.000001"), (0x41b3de4355555553, "333333333.3333332"),
+        (0x41b3de4355555554, "333333333.33333325"), (0x41b3de4355555555, "333333333.3333333"),
+        (0x41b3de4355555556, "333333333.3333334"), (0x41b3de4355555557, "333333333.33333343"),
+        (0xbecbf647612f3696, "-0.0000033333333333333333"), (0x43143ff3c1cb0959, "1424953923781206.2"),
+    ];
+    for &(bits, expected) in vectors.iter()
+    {
+        assert_eq!(jcs::to_string(&JsonValue::JsonNumber(f64::from_bits(bits))).unwrap(), expected);
+    }
+    match jcs::to_string(&JsonValue::JsonNumber(f64::NAN))
+    {
+        Err(SerializeError::NonFiniteNumber(n)) => assert!(n.is_nan()),
+        result => panic!("unexpected {:?}", result),
+    }
+
+    // raw values are canonicalized, and key order and formatting do not matter
+    let first = JsonParser::new("{\"b\": [1.0, {\"y\": 2, \"x\": 1}], \"a\": 1e2}")
+        .with_raw_capture(RawCapture::Fields(vec!["b".to_owned()])).parse().unwrap();
+    let second = JsonParser::new("{\"a\":100,\"b\":[1,{\"x\":1,\"y\":2.00}]}").parse().unwrap();
+    assert_eq!(jcs::to_string(&first).unwrap(), "{\"a\":100,\"b\":[1,{\"x\":1,\"y\":2}]}");
+    assert_eq!(jcs::to_string(&first).unwrap(), jcs::to_string(&second).unwrap());
+}
+
+#[test]
+fn test_fingerprint() {
+    let sha256 = |bytes : &[u8]| {
+        let mut digest = Sha256::default();
+        digest.update(bytes);
+        digest.finish().iter().map(|byte| format!("{:02x}", byte)).collect::<String>()
+    };
+    assert_eq!(sha256(b""), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+    assert_eq!(sha256(b"abc"), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    assert_eq!(sha256(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+               "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1");
+    let mut digest = Sha256::default();
+    for _ in 0..1000
+    {
+        digest.update(&[b'a'; 1000]);
+    }
+    assert_eq!(hash::Fingerprint::from(digest.finish()).to_string(), "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0");
+
+    // key order, formatting and number spelling do not matter
+    let first = JsonParser::new(r#"{"a": 1.0, "b": ["x", {"c": null, "d": -0}]}"#).parse().unwrap();
+    let second = JsonParser::new(r#"{"b":["x",{"d":0,"c":null}],"a":1e0}"#).parse().unwrap();
+    assert_eq!(hash::fingerprint::<Sha256>(&first), hash::fingerprint::<Sha256>(&second));
+    assert_eq!(hash::fingerprint::<Fnv1a64>(&first), hash::fingerprint::<Fnv1a64>(&second));
+    assert_eq!(hash::fingerprint::<Sha256>(&first).as_bytes().len(), 32);
+
+    // but structure and content do
+    let different = [r#"{"a": 1, "b": ["x", {"c": null, "d": 1}]}"#, r#"{"a": 1, "b": [{"c": null, "d": 0}, "x"]}"#,
+                     r#"{"a": 1, "b": ["x", {"c": null}]}"#, r#"{"a": "1", "b": ["x", {"c": null, "d": 0}]}"#,
+                     r#"{"ab": 1, "b": ["x", {"c": null, "d": 0}]}"#, r#"[1, ["x", {"c": null, "d": 0}]]"#];
+    for source in different.iter()
+    {
+        let json = JsonParser::new(source).parse().unwrap();
+        assert!(hash::fingerprint::<Sha256>(&json) != hash::fingerprint::<Sha256>(&first), "{}", source);
+    }
+    assert!(hash::fingerprint::<Sha256>(&JsonValue::JsonArray(vec![])) != hash::fingerprint::<Sha256>(&JsonValue::JsonObject(JsonObject::new())));
+    assert!(hash::fingerprint::<Sha256>(&JsonValue::JsonString("null".to_owned())) != hash::fingerprint::<Sha256>(&JsonValue::Null));
+
+    // every subtree, children first, and shared fragments match across documents
+    let subtrees = hash::subtree_fingerprints::<Sha256>(&first);
+    let paths : Vec<String> = subtrees.iter().map(|&(ref path, _)| path.to_string()).collect();
+    assert_eq!(paths, ["/a", "/b/0", "/b/1/c", "/b/1/d", "/b/1", "/b", ""]);
+    assert_eq!(subtrees[6].1, hash::fingerprint::<Sha256>(&first));
+    let other = JsonParser::new(r#"[{"d": 0, "c": null}, "y"]"#).parse().unwrap();
+    let other_subtrees = hash::subtree_fingerprints::<Sha256>(&other);
+    assert_eq!(other_subtrees[2].0.to_string(), "/0");
+    assert_eq!(other_subtrees[2].1, subtrees[4].1);
+
+    // raw values are hashed as what they hold
+    let raw = JsonParser::new(r#"{"b": ["x", {"d": 0.0, "c": null}], "a": 1}"#)
+        .with_raw_capture(RawCapture::Fields(vec!["b".to_owned()])).parse().unwrap();
+    assert_eq!(hash::subtree_fingerprints::<Sha256>(&raw), subtrees);
+    // text that holds no value is hashed as text, apart from any value
+    let broken = |text : &str| JsonValue::JsonRaw(RawValue::captured(text, ParserOptions::new()));
+    let null = hash::fingerprint::<Sha256>(&JsonValue::Null);
+    assert!(hash::fingerprint::<Sha256>(&broken("nul")) != null);
+    assert!(hash::fingerprint::<Sha256>(&broken("nul")) != hash::fingerprint::<Sha256>(&broken("[")));
+    assert_eq!(hash::fingerprint::<Sha256>(&broken("null")), null);
+}
+
+#[test]
+fn test_owned_containers() {
+    let json = JsonParser::new(r#"{"name": "a", "size": 2, "tags": ["x", true, null], "meta": {"ok": false}}"#).parse().unwrap();
+    let mut object = JsonObjectContainer::try_from(json.clone()).unwrap();
+    assert_eq!(object.len(), 4);
+    assert_eq!(object.get_string_ref("name").unwrap(), Some("a"));
+    assert_eq!(object.get_number("size").unwrap(), Some(2.0));
+    assert_eq!(object.get_number("missing").unwrap(), None);
+    assert!(object.get_number("name").is_err());
+    assert!(object.is_null("missing"));
+    assert_eq!(object.get_object_ref("meta").unwrap().unwrap().get_bool("ok").unwrap(), Some(false));
+    assert_eq!(object.get_array_ref("tags").unwrap().unwrap().len(), 3);
+
+    *object.get_number_mut("size").unwrap().unwrap() += 1.0;
+    object.get_string_mut("name").unwrap().unwrap().push('b');
+    *object.get_array_mut("tags").unwrap().unwrap().get_bool_mut(1).unwrap().unwrap() = false;
+    *object.get_object_mut("meta").unwrap().unwrap().get_bool_mut("ok").unwrap().unwrap() = true;
+    assert_eq!(JsonValue::from(object.clone()).to_json().unwrap(),
+               r#"{"name":"ab","size":3,"tags":["x",false,null],"meta":{"ok":true}}"#);
+
+    let mut tags = object.get("tags").cloned().unwrap().into_array().unwrap();
+    assert_eq!(tags.get_string_ref(0).unwrap(), Some("x"));
+    assert!(tags.get_string_ref(1).is_err());
+    assert!(tags.is_null(2) && tags.is_null(3));
+    tags.get_string_mut(0).unwrap().unwrap().push('y');
+    assert_eq!(tags.iter().count(), 3);
+    assert_eq!(tags.as_slice()[0], JsonValue::JsonString("xy".to_owned()));
+
+    // a value of the wrong type is given back
+    let number = JsonValue::JsonNumber(1.0);
+    assert_eq!(JsonArrayContainer::try_from(number.clone()), Err(number.clone()));
+    assert_eq!(number.clone().into_object(), Err(number));
+    assert_eq!(JsonValue::from(JsonObjectContainer::try_from(json.clone()).unwrap()), json);
+
+    let built = JsonObjectContainer::new()
+        .with_string("name", "b")
+        .with_number("size", 1.5)
+        .with_bool("ok", true)
+        .with_null("none")
+        .with_array("list", JsonArrayContainer::new().with_number(1.0).with_string("two").with_null()
+                            .with_object(JsonObjectContainer::new().with_bool("deep", false)))
+        .with_object("empty", JsonObjectContainer::default())
+        .with("size", JsonValue::JsonNumber(2.0));
+    assert_eq!(JsonValue::from(built.clone()).to_json().unwrap(),
+               r#"{"name":"b","size":2,"ok":true,"none":null,"list":[1,"two",null,{"deep":false}],"empty":{}}"#);
+    assert!(built.contains_key("none") && !built.is_empty());
+    assert_eq!(built.as_container_ref().len(), 6);
+    assert_eq!(built.into_inner().len(), 6);
+}
+
+#[test]
+fn test_container_mutation() {
+    let mut json = JsonParser::new(r#"{"a": 1, "b": [3, 1, 2], "c": "x"}"#).parse().unwrap();
+    {
+        let mut object = json.get_object_mut().unwrap();
+        assert_eq!(object.insert("a", JsonValue::JsonNumber(5.0)), Some(JsonValue::JsonNumber(1.0)));
+        assert_eq!(object.insert("d", JsonValue::Null), None);
+        assert_eq!(object.remove("c"), Some(JsonValue::JsonString("x".to_owned())));
+        assert_eq!(object.remove("c"), None);
+
+        object.insert_string("name", "n").push('!');
+        *object.insert_number("count", 1.0) += 1.0;
+        *object.insert_bool("ok", false) = true;
+        object.insert_null("none");
+        {
+            let mut nested = object.insert_object("nested");
+            nested.insert_array("list").push_number(1.0);
+            nested.insert_bool("deep", true);
+        }
+
+        *object.entry("count").or_insert(JsonValue::Null).get_number_mut().unwrap() += 1.0;
+        object.entry("fresh").or_insert_with(|| JsonValue::JsonBool(true));
+        object.entry("a").and_modify(|value| *value = JsonValue::JsonNumber(6.0)).or_insert(JsonValue::Null);
+        object.entry("missing").and_modify(|_| panic!("no value to modify"));
+        assert_eq!(object.entry("a").key(), "a");
+        match object.entry("none")
+        {
+            Entry::Occupied(entry) => assert_eq!(entry.remove(), JsonValue::Null),
+            Entry::Vacant(_) => panic!("expected a member"),
+        }
+
+        // an existing key keeps its position
+        object.insert_string("a", "first");
+        object.retain(|key, _| key != "d");
+    }
+    assert_eq!(json.to_json().unwrap(),
+               r#"{"a":"first","b":[3,1,2],"name":"n!","count":3,"ok":true,"nested":{"list":[1],"deep":true},"fresh":true}"#);
+
+    {
+        let mut object = json.get_object_mut().unwrap();
+        let mut array = object.get_array_mut("b").unwrap().unwrap();
+        array.sort_by(|a, b| a.get_number().unwrap().partial_cmp(&b.get_number().unwrap()).unwrap());
+        array.push(JsonValue::JsonNumber(4.0));
+        assert_eq!(array.pop(), Some(JsonValue::JsonNumber(4.0)));
+        assert_eq!(array.insert(0, JsonValue::JsonString("zero".to_owned())), Ok(()));
+        assert_eq!(array.insert(5, JsonValue::JsonBool(true)), Err(JsonValue::JsonBool(true)));
+        assert_eq!(array.remove(1), Some(JsonValue::JsonNumber(1.0)));
+        assert_eq!(array.remove(10), None);
+        array.push_string("s").push('t');
+        *array.push_number(7.0) *= 2.0;
+        *array.push_bool(true) = false;
+        array.push_null();
+        array.push_array().push_object().insert_number("x", 1.0);
+        array.push_object().insert_array("y").push_null();
+        assert_eq!(array.len(), 9);
+        array.retain(|value| !value.is_null());
+        array.truncate(7);
+    }
+    assert_eq!(json.get_object_ref().unwrap().get_array_ref("b").unwrap().unwrap().len(), 7);
+    let array = json.get_object_ref().unwrap().get_array_ref("b").unwrap().unwrap().get_array_ref(6).unwrap().unwrap().len();
+    assert_eq!(array, 1);
+    let b = match json.get_object_mut().unwrap().remove("b")
+    {
+        Some(b) => b,
+        None => panic!("expected b"),
+    };
+    assert_eq!(b.to_json().unwrap(), r#"["zero",2,3,"st",14,false,[{"x":1}]]"#);
+
+    // owned containers are changed through their mut refs
+    let mut owned = JsonArrayContainer::new();
+    owned.as_container_mut().push_object().insert_string("k", "v");
+    assert!(!owned.as_container_ref().is_empty());
+    owned.as_container_mut().clear();
+    assert!(owned.is_empty());
+    assert!(owned.as_container_mut().is_empty());
+}
@@ -0,0 +1,267 @@
+use json::JsonValue;
+use raw::RawValue;
+use ser::{self,IoAdapter,SerializeError,SerializerOptions};
+use std::fmt::{self, Write};
+use std::{io, mem};
+
+/// Writes a JSON document piece by piece to an `io::Write`
+///
+/// Large documents can be written without building a `JsonValue`.
+/// Each call is checked against the structure written so far, and a
+/// call that would make the output malformed fails without writing
+/// anything: a key outside an object, a value where a key is due,
+/// closing the wrong container or a second document. `finish` fails
+/// if the document is incomplete. A value that fails part way through,
+/// such as an array holding NaN, leaves the output incomplete.
+pub struct JsonWriter<W : io::Write>
+{
+    out : W,
+    options : SerializerOptions,
+    /// The open containers, and whether each has a member or element yet
+    stack : Vec<(Scope, bool)>,
+    /// The document's value is complete
+    done : bool,
+    /// Characters written since the last line ending
+    column : usize,
+}
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+enum Scope
+{
+    /// An object, waiting for a key or, after one, its value
+    Object{after_key : bool},
+    Array,
+}
+
+/// Sends output to the writer's sink and keeps track of the column
+struct Tracked<'a, 'w : 'a, W : 'w>
+{
+    adapter : IoAdapter<'w, W>,
+    column : &'a mut usize,
+}
+
+impl<'a, 'w, W : io::Write> Write for Tracked<'a, 'w, W>
+{
+    fn write_str(&mut self, s : &str) -> fmt::Result
+    {
+        match s.rfind('\n')
+        {
+            Some(i) => *self.column = s[i + 1..].chars().count(),
+            None => *self.column += s.chars().count(),
+        }
+        self.adapter.write_str(s)
+    }
+}
+
+impl<W : io::Write> JsonWriter<W>
+{
+    /// A writer of compact JSON to `out`
+    pub fn new(out : W) -> JsonWriter<W>
+    {
+        JsonWriter{out, options : SerializerOptions::new(), stack : Vec::new(), done : false, column : 0}
+    }
+
+    /// Write as `options` say, for example pretty-printed
+    ///
+    /// Objects and arrays opened with `begin_object` and `begin_array`
+    /// always span lines in pretty output, since their contents are not
+    /// known in advance; values given to `value` are laid out in full.
+    pub fn with_options(mut self, options : SerializerOptions) -> JsonWriter<W>
+    {
+        self.options = options;
+        self
+    }
+
+    pub fn begin_object(&mut self) -> Result<(), SerializeError>
+    {
+        self.before_value()?;
+        self.emit(|out, _| out.write_char('{').map_err(SerializeError::from))?;
+        self.stack.push((Scope::Object{after_key : false}, false));
+        Ok(())
+    }
+
+    /// Write the key of the next member of the innermost object
+    pub fn key(&mut self, key : &str) -> Result<(), SerializeError>
+    {
+        match self.stack.last()
+        {
+            Some(&(Scope::Object{after_key : false}, _)) => {},
+            Some(&(Scope::Object{after_key : true}, _)) => return Err(SerializeError::InvalidStructure("expected a value after a key")),
+            _ => return Err(SerializeError::InvalidStructure("key outside an object")),
+        }
+        self.separate()?;
+        let space = self.options.pretty.as_ref().map_or(false, |pretty| pretty.space_after_colon);
+        self.emit(|out, options|
+        {
+            ser::write_string(key, options.escaping, out)?;
+            out.write_str(if space {": "} else {":"})?;
+            Ok(())
+        })?;
+        if let Some(&mut (ref mut scope, _)) = self.stack.last_mut()
+        {
+            *scope = Scope::Object{after_key : true};
+        }
+        Ok(())
+    }
+
+    pub fn end_object(&mut self) -> Result<(), SerializeError>
+    {
+        match self.stack.last()
+        {
+            Some(&(Scope::Object{after_key : false}, _)) => self.close('}'),
+            Some(&(Scope::Object{after_key : true}, _)) => Err(SerializeError::InvalidStructure("expected a value after a key")),
+            _ => Err(SerializeError::InvalidStructure("no object to end")),
+        }
+    }
+
+    pub fn begin_array(&mut self) -> Result<(), SerializeError>
+    {
+        self.before_value()?;
+        self.emit(|out, _| out.write_char('[').map_err(SerializeError::from))?;
+        self.stack.push((Scope::Array, false));
+        Ok(())
+    }
+
+    pub fn end_array(&mut self) -> Result<(), SerializeError>
+    {
+        match self.stack.last()
+        {
+            Some(&(Scope::Array, _)) => self.close(']'),
+            _ => Err(SerializeError::InvalidStructure("no array to end")),
+        }
+    }
+
+    /// Write a complete value
+    pub fn value(&mut self, value : &JsonValue) -> Result<(), SerializeError>
+    {
+        self.before_value()?;
+        let depth = self.stack.len();
+        self.emit(|out, options|
+        {
+            let column = *out.column;
+            ser::write_nested(value, out, options, depth, column)
+        })?;
+        self.after_value();
+        Ok(())
+    }
+
+    /// Write a string value, without building a `JsonValue`
+    pub fn string(&mut self, string : &str) -> Result<(), SerializeError>
+    {
+        self.before_value()?;
+        self.emit(|out, options| ser::write_string(string, options.escaping, out).map_err(SerializeError::from))?;
+        self.after_value();
+        Ok(())
+    }
+
+    /// Write `fragment`, the text of a single JSON value, as it is
+    ///
+    /// Like a raw value in a document, the fragment is written again
+    /// when the options ask for more escapes or for pretty containers
+    pub fn raw(&mut self, fragment : &str) -> Result<(), SerializeError>
+    {
+        let raw = match RawValue::new(fragment)
+        {
+            Ok(raw) => raw,
+            Err(error) => return Err(SerializeError::InvalidFragment(format!("{:?}", error))),
+        };
+        self.value(&JsonValue::JsonRaw(raw))
+    }
+
+    /// Check that the document is complete and return the sink
+    pub fn finish(mut self) -> Result<W, SerializeError>
+    {
+        if !self.done
+        {
+            return Err(SerializeError::InvalidStructure("unfinished document"));
+        }
+        if let Some(pretty) = self.options.pretty.clone()
+        {
+            if pretty.final_newline
+            {
+                self.emit(|out, _| ser::new_line(&pretty, 0, out).map_err(SerializeError::from))?;
+            }
+        }
+        self.out.flush().map_err(SerializeError::Io)?;
+        Ok(self.out)
+    }
+
+    /// Check that a value may come next and write what goes before it
+    fn before_value(&mut self) -> Result<(), SerializeError>
+    {
+        match self.stack.last_mut()
+        {
+            None if self.done => return Err(SerializeError::InvalidStructure("a document has a single value")),
+            None => return Ok(()),
+            Some(&mut (Scope::Object{after_key : false}, _)) => return Err(SerializeError::InvalidStructure("expected a key")),
+            Some(&mut (ref mut scope @ Scope::Object{..}, _)) =>
+            {
+                *scope = Scope::Object{after_key : false};
+                return Ok(());
+            },
+            Some(&mut (Scope::Array, _)) => {},
+        }
+        self.separate()
+    }
+
+    fn after_value(&mut self)
+    {
+        self.done = self.stack.is_empty();
+    }
+
+    /// The comma and line break before a member or element
+    fn separate(&mut self) -> Result<(), SerializeError>
+    {
+        let depth = self.stack.len();
+        let started = match self.stack.last_mut()
+        {
+            Some(&mut (_, ref mut started)) => mem::replace(started, true),
+            None => false,
+        };
+        self.emit(|out, options|
+        {
+            if started
+            {
+                out.write_char(',')?;
+            }
+            if let Some(ref pretty) = options.pretty
+            {
+                ser::new_line(pretty, depth, out)?;
+            }
+            Ok(())
+        })
+    }
+
+    fn close(&mut self, bracket : char) -> Result<(), SerializeError>
+    {
+        let started = match self.stack.pop()
+        {
+            Some((_, started)) => started,
+            None => false,
+        };
+        let depth = self.stack.len();
+        self.emit(|out, options|
+        {
+            if let (true, Some(pretty)) = (started, options.pretty.as_ref())
+            {
+                ser::new_line(pretty, depth, out)?;
+            }
+            out.write_char(bracket)?;
+            Ok(())
+        })?;
+        self.after_value();
+        Ok(())
+    }
+
+    /// Run `write` on the sink, turning a failure of the sink into its I/O error
+    fn emit<F>(&mut self, write : F) -> Result<(), SerializeError>
+        where F : FnOnce(&mut Tracked<W>, &SerializerOptions) -> Result<(), SerializeError>
+    {
+        let mut out = Tracked{adapter : IoAdapter{writer : &mut self.out, error : None}, column : &mut self.column};
+        match write(&mut out, &self.options)
+        {
+            Err(SerializeError::Fmt(error)) => Err(out.adapter.error.map_or(SerializeError::Fmt(error), SerializeError::Io)),
+            result => result,
+        }
+    }
+}